@@ -1,6 +1,13 @@
+use std::rc::Rc;
+
 use crate::float::Float;
+use crate::ray::Ray;
+use crate::hit::Hit;
 use crate::hitable::Hitable;
+use crate::hitable::primitive::Group;
+use crate::hitable::transform::{Instance, OneSided};
 use crate::material::Material;
+use crate::utils::step_past;
 
 pub struct Actor<T>
     where T: Float
@@ -8,3 +15,125 @@ pub struct Actor<T>
     pub hitable: Box<Hitable<T>>,
     pub material: Box<Material<T>>
 }
+
+impl<T> Actor<T>
+    where T: Float
+{
+    pub fn new(hitable: Box<dyn Hitable<T>>, material: Box<dyn Material<T>>) -> Self {
+        Actor {
+            hitable,
+            material
+        }
+    }
+
+    pub fn is_one_sided(&self) -> bool {
+        self.hitable.is_one_sided()
+    }
+
+    // Hits on the back face are culled, e.g. for light portals
+    pub fn set_one_sided(&mut self, one_sided: bool) {
+        if one_sided == self.is_one_sided() {
+            return;
+        }
+        let hitable = std::mem::replace(&mut self.hitable, Box::new(Group::new()));
+        self.hitable = if one_sided {
+            Box::new(OneSided::new(hitable))
+        } else {
+            hitable.unwrap()
+        };
+    }
+
+    pub fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        // The nearest hit on an opaque part of the surface
+        let mut t_min = t_min;
+        loop {
+            let hit = self.hitable.hit(ray, t_min, t_max)?;
+            if self.material.is_opaque(&hit) {
+                return Some(hit);
+            }
+            t_min = step_past(hit.t);
+        }
+    }
+
+    pub fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        // Transparent hits need the full search
+        if self.material.is_masked() {
+            return self.hit(ray, t_min, t_max).is_some();
+        }
+        self.hitable.any_hit(ray, t_min, t_max)
+    }
+}
+
+// A hitable and its material, meant to be placed in the scene many times.
+// Every instance becomes its own actor, but they all share the same geometry.
+pub struct SharedGeometry<T>
+    where T: Float
+{
+    hitable: Rc<dyn Hitable<T>>,
+    material: Rc<dyn Material<T>>
+}
+
+impl<T> SharedGeometry<T>
+    where T: Float
+{
+    pub fn new(hitable: Box<dyn Hitable<T>>, material: Box<dyn Material<T>>) -> Self {
+        SharedGeometry {
+            hitable: Rc::from(hitable),
+            material: Rc::from(material)
+        }
+    }
+
+    pub fn get_hitable(&self) -> &Rc<dyn Hitable<T>> {
+        &self.hitable
+    }
+
+    pub fn get_material(&self) -> &Rc<dyn Material<T>> {
+        &self.material
+    }
+
+    pub fn create_instance(&self) -> Instance<T> {
+        Instance::new(Rc::clone(&self.hitable))
+    }
+
+    pub fn create_actor(&self, instance: Instance<T>, material: Option<Box<dyn Material<T>>>) -> Actor<T> {
+        // The shared material is used unless the instance overrides it
+        let material = match material {
+            Some(material) => material,
+            None => Box::new(Rc::clone(&self.material))
+        };
+        Actor::new(Box::new(instance), material)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Vec3;
+    use crate::constants::Axis;
+    use crate::hitable::primitive::Rectangle;
+    use crate::material::plain::PlainMaterial;
+    use crate::texture::uniform::UniformTexture;
+
+    #[test]
+    fn one_sided() {
+        // The rectangle faces +Z
+        let hitable = Box::new(Rectangle::<f64>::new(2.0, Axis::X, 2.0, Axis::Y));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+        let mut actor = Actor::new(hitable, Box::new(PlainMaterial::new(texture)));
+
+        let front = Ray::from_array([0.0, 0.0, 5.0], [0.0, 0.0, -1.0]);
+        let back = Ray::from_array([0.0, 0.0, -5.0], [0.0, 0.0, 1.0]);
+        assert!(actor.hit(&front, 0.0, 100.0).is_some());
+        assert!(actor.hit(&back, 0.0, 100.0).is_some());
+
+        actor.set_one_sided(true);
+        assert!(actor.is_one_sided());
+        assert!(actor.hit(&front, 0.0, 100.0).is_some());
+        assert!(actor.hit(&back, 0.0, 100.0).is_none());
+        assert!(!actor.any_hit(&back, 0.0, 100.0));
+
+        actor.set_one_sided(false);
+        assert!(!actor.is_one_sided());
+        assert!(actor.hit(&back, 0.0, 100.0).is_some());
+    }
+}
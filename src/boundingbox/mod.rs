@@ -2,6 +2,7 @@ use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
 
+#[derive(Clone)]
 pub struct BoundingBox<T>
     where T : Float
 {
@@ -26,12 +27,24 @@ impl<T> BoundingBox<T>
     }
 
     pub fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        self.intersect(ray, t_min, t_max).is_some()
+    }
+
+    pub fn intersect(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(T, T)> {
+        let direction = ray.get_direction().get_data();
+        let inv_direction = [T::one() / direction[0], T::one() / direction[1], T::one() / direction[2]];
+        self.intersect_inverse(ray.get_origin().get_data(), &inv_direction, t_min, t_max)
+    }
+
+    pub fn intersect_inverse(&self, origin: &[T], inv_direction: &[T], t_min: T, t_max: T) -> Option<(T, T)> {
+        // The part of [t_min, t_max] where the ray is inside the box.
+        // Takes the inverse of the ray direction, so that rays tested against many boxes only compute it once.
         let mut t_min = t_min;
         let mut t_max = t_max;
         for i in 0..3 {
-            let inv_d = T::one() / ray.get_direction().get_data()[i];
-            let mut t0 = (self.p0.get_data()[i] - ray.get_origin().get_data()[i]) * inv_d;
-            let mut t1 = (self.p1.get_data()[i] - ray.get_origin().get_data()[i]) * inv_d;
+            let inv_d = inv_direction[i];
+            let mut t0 = (self.p0.get_data()[i] - origin[i]) * inv_d;
+            let mut t1 = (self.p1.get_data()[i] - origin[i]) * inv_d;
             if inv_d < T::zero() {
                 let tmp = t0;
                 t0 = t1;
@@ -39,7 +52,25 @@ impl<T> BoundingBox<T>
             }
             t_min = if t0 > t_min { t0 } else { t_min };
             t_max = if t1 < t_max { t1 } else { t_max };
-            if t_max <= t_min {
+            // Flat boxes (e.g. around a rectangle) have t_max == t_min when hit
+            if t_max < t_min {
+                return None;
+            }
+        }
+        Some((t_min, t_max))
+    }
+
+    pub fn infinite() -> Self {
+        // The bounds of unbounded hitables, such as infinite planes
+        let p0 = Vec3::from_array([T::neg_infinity(); 3]);
+        let p1 = Vec3::from_array([T::infinity(); 3]);
+        BoundingBox {p0, p1}
+    }
+
+    pub fn is_finite(&self) -> bool {
+        for i in 0..3 {
+            let (min, max) = self.get_axis_bounds(i);
+            if !min.is_finite() || !max.is_finite() {
                 return false;
             }
         }
@@ -104,6 +135,14 @@ impl<T> BoundingBox<T>
         volume
     }
 
+    pub fn get_area(&self) -> T {
+        // The surface area of the box
+        let x = self.get_axis_length(0);
+        let y = self.get_axis_length(1);
+        let z = self.get_axis_length(2);
+        (x * y + y * z + z * x) * T::from(2.0).unwrap()
+    }
+
     pub fn get_axis_bounds(&self, axis: usize) -> (T, T) {
         let min = self.p0.get_data()[axis];
         let max = self.p1.get_data()[axis];
@@ -251,6 +290,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn flat() {
+        let p0 = Vec3::from_array([-1.0, -1.0, 0.0]);
+        let p1 = Vec3::from_array([1.0, 1.0, 0.0]);
+        let box0 = BoundingBox::new(p0, p1);
+
+        let ray = Ray::from_array([0.5, 0.5, 2.0], [0.0, 0.0, -1.0]);
+        assert!(box0.hit(&ray, 0.0, 100.0));
+        let ray = Ray::from_array([1.5, 0.5, 2.0], [0.0, 0.0, -1.0]);
+        assert!(!box0.hit(&ray, 0.0, 100.0));
+    }
+
+    #[test]
+    fn finite() {
+        let p0 = Vec3::from_array([0.0, 0.0, 0.0]);
+        let p1 = Vec3::from_array([5.0, 4.0, 3.0]);
+        let box0 = BoundingBox::new(p0, p1);
+        assert!(box0.is_finite());
+
+        let box1 = BoundingBox::<f64>::infinite();
+        assert!(!box1.is_finite());
+        assert!(box1.contains(&box0));
+    }
+
     #[test]
     fn volume() {
         let p0 = Vec3::from_array([-1.0, 2.0, -4.0]);
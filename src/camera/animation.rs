@@ -0,0 +1,316 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::camera::Camera;
+
+pub trait CameraAnimation<T>
+    where T: Float
+{
+    fn get_duration(&self) -> T;
+
+    // Whether the camera ends where it started, i.e. the last frame of a sequence would repeat the first one
+    fn is_looping(&self) -> bool;
+
+    fn apply(&self, camera: &mut dyn Camera<T>, time: T);
+}
+
+#[derive(Copy, Clone)]
+pub enum Interpolation {
+    Linear,
+    Spline // Catmull-Rom spline passing through every keyframe
+}
+
+#[derive(Clone)]
+pub struct Keyframe<T>
+    where T: Float
+{
+    pub time: T,
+    pub position: Vec3<T>,
+    pub lookat: Vec3<T>,
+    pub fov: T,
+    pub focus: T
+}
+
+pub struct KeyframeAnimation<T>
+    where T: Float
+{
+    keyframes: Vec<Keyframe<T>>,
+    interpolation: Interpolation
+}
+
+impl<T> KeyframeAnimation<T>
+    where T: Float
+{
+    pub fn new(interpolation: Interpolation) -> Self {
+        KeyframeAnimation {
+            keyframes: vec![],
+            interpolation
+        }
+    }
+
+    pub fn add_keyframe(&mut self, keyframe: Keyframe<T>) {
+        // Keep the keyframes sorted by time
+        let index = self.keyframes.iter().position(|k| k.time > keyframe.time).unwrap_or(self.keyframes.len());
+        self.keyframes.insert(index, keyframe);
+    }
+
+    pub fn get_keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    pub fn get_keyframe(&self, time: T) -> Keyframe<T> {
+        assert!(!self.keyframes.is_empty(), "The animation has no keyframes");
+        let last = self.keyframes.len() - 1;
+
+        if time <= self.keyframes[0].time {
+            return self.keyframes[0].clone();
+        }
+        if time >= self.keyframes[last].time {
+            return self.keyframes[last].clone();
+        }
+
+        let i = self.keyframes.iter().position(|k| k.time > time).unwrap() - 1;
+        let k1 = &self.keyframes[i];
+        let k2 = &self.keyframes[i + 1];
+        let t = (time - k1.time) / (k2.time - k1.time);
+
+        match self.interpolation {
+            Interpolation::Linear => {
+                Keyframe {
+                    time,
+                    position: lerp_vec(&k1.position, &k2.position, t),
+                    lookat: lerp_vec(&k1.lookat, &k2.lookat, t),
+                    fov: lerp(k1.fov, k2.fov, t),
+                    focus: lerp(k1.focus, k2.focus, t)
+                }
+            },
+            Interpolation::Spline => {
+                // The first and last keyframes are repeated to provide the outer control points
+                let k0 = &self.keyframes[if i > 0 { i - 1 } else { i }];
+                let k3 = &self.keyframes[if i + 2 <= last { i + 2 } else { last }];
+                Keyframe {
+                    time,
+                    position: spline_vec(&k0.position, &k1.position, &k2.position, &k3.position, t),
+                    lookat: spline_vec(&k0.lookat, &k1.lookat, &k2.lookat, &k3.lookat, t),
+                    fov: spline(k0.fov, k1.fov, k2.fov, k3.fov, t),
+                    focus: spline(k0.focus, k1.focus, k2.focus, k3.focus, t)
+                }
+            }
+        }
+    }
+}
+
+impl<T> CameraAnimation<T> for KeyframeAnimation<T>
+    where T: Float
+{
+    fn get_duration(&self) -> T {
+        match self.keyframes.last() {
+            Some(keyframe) => keyframe.time,
+            None => T::zero()
+        }
+    }
+
+    fn is_looping(&self) -> bool {
+        false
+    }
+
+    fn apply(&self, camera: &mut dyn Camera<T>, time: T) {
+        let keyframe = self.get_keyframe(time);
+        camera.set_position(keyframe.position.get_data());
+        camera.set_lookat(keyframe.lookat.get_data());
+        camera.set_fov(keyframe.fov);
+        camera.set_focus(keyframe.focus);
+    }
+}
+
+// Orbits the camera around a target point, keeping it in focus
+pub struct Turntable<T>
+    where T: Float
+{
+    target: Vec3<T>,
+    position: Vec3<T>, // The position of the camera at the start of the animation
+    axis: Vec3<T>,
+    turns: T,
+    duration: T
+}
+
+impl<T> Turntable<T>
+    where T: Float
+{
+    pub fn new(target: Vec3<T>, position: Vec3<T>, axis: Vec3<T>, duration: T) -> Self {
+        assert!(duration > T::zero(), "A turntable needs a positive duration");
+        let mut axis = axis;
+        axis.normalize();
+        Turntable {
+            target,
+            position,
+            axis,
+            turns: T::one(),
+            duration
+        }
+    }
+
+    pub fn get_turns(&self) -> T {
+        self.turns
+    }
+
+    pub fn set_turns(&mut self, turns: T) {
+        self.turns = turns;
+    }
+
+    pub fn get_position(&self, time: T) -> Vec3<T> {
+        // Rodrigues' rotation of the starting offset around the axis
+        let two_pi = T::from(2.0 * std::f64::consts::PI).unwrap();
+        let angle = two_pi * self.turns * time / self.duration;
+        let (sin, cos) = (angle.sin(), angle.cos());
+        let offset = &self.position - &self.target;
+        let rotated = &offset * cos
+            + self.axis.cross(&offset) * sin
+            + &self.axis * (self.axis.dot(&offset) * (T::one() - cos));
+        &self.target + rotated
+    }
+}
+
+impl<T> CameraAnimation<T> for Turntable<T>
+    where T: Float
+{
+    fn get_duration(&self) -> T {
+        self.duration
+    }
+
+    fn is_looping(&self) -> bool {
+        self.turns == self.turns.round()
+    }
+
+    fn apply(&self, camera: &mut dyn Camera<T>, time: T) {
+        let position = self.get_position(time);
+        let focus = (&position - &self.target).norm();
+        camera.set_position(position.get_data());
+        camera.set_lookat(self.target.get_data());
+        camera.set_focus(focus);
+    }
+}
+
+fn lerp<T>(a: T, b: T, t: T) -> T
+    where T: Float
+{
+    a + (b - a) * t
+}
+
+fn lerp_vec<T>(a: &Vec3<T>, b: &Vec3<T>, t: T) -> Vec3<T>
+    where T: Float
+{
+    a + (b - a) * t
+}
+
+fn spline<T>(p0: T, p1: T, p2: T, p3: T, t: T) -> T
+    where T: Float
+{
+    let half = T::from(0.5).unwrap();
+    let two = T::from(2.0).unwrap();
+    let three = T::from(3.0).unwrap();
+    let four = T::from(4.0).unwrap();
+    let five = T::from(5.0).unwrap();
+    let t2 = t * t;
+    let t3 = t2 * t;
+    half * (
+        two * p1
+        + (p2 - p0) * t
+        + (two * p0 - five * p1 + four * p2 - p3) * t2
+        + (three * p1 - p0 - three * p2 + p3) * t3
+    )
+}
+
+fn spline_vec<T>(p0: &Vec3<T>, p1: &Vec3<T>, p2: &Vec3<T>, p3: &Vec3<T>, t: T) -> Vec3<T>
+    where T: Float
+{
+    let mut result = Vec3::<T>::new();
+    for i in 0..3 {
+        result.get_data_mut()[i] = spline(p0.get_data()[i], p1.get_data()[i], p2.get_data()[i], p3.get_data()[i], t);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::perspective::PerspectiveCamera;
+
+    fn keyframe(time: f64, x: f64, fov: f64) -> Keyframe<f64> {
+        Keyframe {
+            time,
+            position: Vec3::from_array([x, -10.0, 0.0]),
+            lookat: Vec3::from_array([x, 0.0, 0.0]),
+            fov,
+            focus: 10.0
+        }
+    }
+
+    #[test]
+    fn linear() {
+        let mut animation = KeyframeAnimation::<f64>::new(Interpolation::Linear);
+        animation.add_keyframe(keyframe(2.0, 4.0, 1.0));
+        animation.add_keyframe(keyframe(0.0, 0.0, 0.5));
+        assert_eq!(animation.get_duration(), 2.0);
+        assert!(!animation.is_looping());
+
+        let k = animation.get_keyframe(0.5);
+        assert_eq!(k.position.get_data(), [1.0, -10.0, 0.0]);
+        assert_eq!(k.fov, 0.625);
+
+        // Times outside the animation are clamped to the first and last keyframes
+        let k = animation.get_keyframe(-1.0);
+        assert_eq!(k.position.get_data(), [0.0, -10.0, 0.0]);
+        let k = animation.get_keyframe(3.0);
+        assert_eq!(k.position.get_data(), [4.0, -10.0, 0.0]);
+
+        let mut camera = PerspectiveCamera::<f64>::new();
+        animation.apply(&mut camera, 1.0);
+        assert_eq!(camera.get_position().get_data(), [2.0, -10.0, 0.0]);
+        assert_eq!(camera.get_lookat().get_data(), [2.0, 0.0, 0.0]);
+        assert_eq!(camera.get_fov(), 0.75);
+        assert_eq!(camera.get_focus(), 10.0);
+    }
+
+    #[test]
+    fn spline() {
+        let mut animation = KeyframeAnimation::<f64>::new(Interpolation::Spline);
+        animation.add_keyframe(keyframe(0.0, 0.0, 0.5));
+        animation.add_keyframe(keyframe(1.0, 1.0, 0.5));
+        animation.add_keyframe(keyframe(2.0, 0.0, 0.5));
+
+        // The spline passes through the keyframes
+        for i in 0..3 {
+            let expected = animation.get_keyframes()[i].position.get_data()[0];
+            let k = animation.get_keyframe(i as f64);
+            assert!((k.position.get_data()[0] - expected).abs() < 1e-12);
+        }
+
+        // And is smooth around the peak, unlike linear interpolation
+        let k = animation.get_keyframe(0.9);
+        assert!(k.position.get_data()[0] > 0.9);
+        assert!(k.position.get_data()[0] < 1.0);
+    }
+
+    #[test]
+    fn turntable() {
+        let target = Vec3::from_array([1.0, 1.0, 0.0]);
+        let position = Vec3::from_array([1.0, -1.0, 2.0]);
+        let axis = Vec3::from_array([0.0, 0.0, 2.0]);
+        let mut turntable = Turntable::<f64>::new(target, position, axis, 4.0);
+        assert!(turntable.is_looping());
+
+        let mut camera = PerspectiveCamera::<f64>::new();
+        let expected = [[1.0, -1.0, 2.0], [3.0, 1.0, 2.0], [1.0, 3.0, 2.0], [-1.0, 1.0, 2.0]];
+        for i in 0..4 {
+            turntable.apply(&mut camera, i as f64);
+            for j in 0..3 {
+                assert!((camera.get_position().get_data()[j] - expected[i][j]).abs() < 1e-12);
+            }
+            assert_eq!(camera.get_lookat().get_data(), [1.0, 1.0, 0.0]);
+            assert!((camera.get_focus() - 8.0f64.sqrt()).abs() < 1e-12);
+        }
+
+        turntable.set_turns(0.5);
+        assert!(!turntable.is_looping());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::float::Float;
+use crate::utils::{random, random_point_in_circle};
+
+// The shape of the lens opening, which determines the shape of the bokeh.
+// Samples are points in the lens plane, within the square [-1, 1] x [-1, 1]
+// (within the unit circle for the circular and polygonal apertures);
+// cameras scale them by the aperture radius along their u/v axes.
+pub trait Aperture<T>
+    where T: Float
+{
+    fn sample(&self) -> (T, T);
+}
+
+pub struct CircularAperture {}
+
+impl CircularAperture {
+    pub fn new() -> Self {
+        CircularAperture {}
+    }
+}
+
+impl<T> Aperture<T> for CircularAperture
+    where T: Float
+{
+    fn sample(&self) -> (T, T) {
+        let point = random_point_in_circle(T::one());
+        (point.get_data()[0], point.get_data()[1])
+    }
+}
+
+// A regular polygon inscribed in the unit circle, like the opening of an iris with n blades
+pub struct PolygonAperture<T>
+    where T: Float
+{
+    blades: usize,
+    rotation: T
+}
+
+impl<T> PolygonAperture<T>
+    where T: Float
+{
+    pub fn new(blades: usize, rotation: T) -> Self {
+        assert!(blades >= 3, "A polygonal aperture needs at least 3 blades");
+        PolygonAperture {
+            blades,
+            rotation
+        }
+    }
+
+    fn get_vertex(&self, index: usize) -> (T, T) {
+        let two_pi = T::from(2.0 * std::f64::consts::PI).unwrap();
+        let angle = self.rotation + two_pi * T::from(index).unwrap() / T::from(self.blades).unwrap();
+        (angle.cos(), angle.sin())
+    }
+}
+
+impl<T> Aperture<T> for PolygonAperture<T>
+    where T: Float
+{
+    fn sample(&self) -> (T, T) {
+        // The polygon is a fan of identical triangles around the center,
+        // pick one of them and then a uniform point inside it
+        let index = (random::<T>() * T::from(self.blades).unwrap()).to_usize().unwrap() % self.blades;
+        let (x0, y0) = self.get_vertex(index);
+        let (x1, y1) = self.get_vertex(index + 1);
+
+        let mut a = random::<T>();
+        let mut b = random::<T>();
+        if a + b > T::one() {
+            a = T::one() - a;
+            b = T::one() - b;
+        }
+        (a * x0 + b * x1, a * y0 + b * y1)
+    }
+}
+
+// An arbitrary aperture described by a grayscale mask spanning the square [-1, 1] x [-1, 1].
+// Mask values are the transmission of the lens, between 0 (opaque) and 1 (fully open).
+pub struct MaskAperture<T>
+    where T: Float
+{
+    width: usize,
+    height: usize,
+    data: Vec<T>
+}
+
+impl<T> MaskAperture<T>
+    where T: Float
+{
+    const MAX_ATTEMPTS: usize = 1024;
+
+    pub fn new(width: usize, height: usize, data: Vec<T>) -> Self {
+        assert_eq!(data.len(), width * height);
+        MaskAperture {
+            width,
+            height,
+            data
+        }
+    }
+
+    fn get_transmission(&self, x: T, y: T) -> T {
+        let half = T::from(0.5).unwrap();
+        let i = ((x + T::one()) * half * T::from(self.width).unwrap()).to_usize().unwrap();
+        let j = ((y + T::one()) * half * T::from(self.height).unwrap()).to_usize().unwrap();
+        let i = if i < self.width { i } else { self.width - 1 };
+        let j = if j < self.height { j } else { self.height - 1 };
+        self.data[j * self.width + i]
+    }
+}
+
+impl<T> Aperture<T> for MaskAperture<T>
+    where T: Float
+{
+    fn sample(&self) -> (T, T) {
+        let two = T::from(2.0).unwrap();
+        // Rejection sampling, points are kept proportionally to the mask transmission
+        for _ in 0..Self::MAX_ATTEMPTS {
+            let x = random::<T>() * two - T::one();
+            let y = random::<T>() * two - T::one();
+            if random::<T>() < self.get_transmission(x, y) {
+                return (x, y);
+            }
+        }
+        // The mask is (almost) completely opaque, behave like a pinhole
+        (T::zero(), T::zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circle() {
+        let aperture = CircularAperture::new();
+        for _ in 0..100 {
+            let (x, y): (f64, f64) = aperture.sample();
+            assert!(x * x + y * y <= 1.0);
+        }
+    }
+
+    #[test]
+    fn polygon() {
+        // A square aperture rotated by 45 degrees has its sides aligned with the axes
+        let aperture = PolygonAperture::<f64>::new(4, 0.25 * std::f64::consts::PI);
+        let half_side = 0.5 * 2.0f64.sqrt() + 1e-12;
+        for _ in 0..100 {
+            let (x, y) = aperture.sample();
+            assert!(x.abs() <= half_side);
+            assert!(y.abs() <= half_side);
+        }
+    }
+
+    #[test]
+    fn mask() {
+        // Only the top right quadrant of the mask is open
+        let data = vec![0.0, 0.0, 0.0, 1.0];
+        let aperture = MaskAperture::<f64>::new(2, 2, data);
+        for _ in 0..100 {
+            let (x, y) = aperture.sample();
+            assert!(x >= 0.0 && x <= 1.0);
+            assert!(y >= 0.0 && y <= 1.0);
+        }
+
+        let data = vec![0.0; 4];
+        let aperture = MaskAperture::<f64>::new(2, 2, data);
+        assert_eq!(aperture.sample(), (0.0, 0.0));
+    }
+}
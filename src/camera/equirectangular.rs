@@ -0,0 +1,188 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::camera::{Camera, CameraLock};
+use crate::utils::orthonormal_basis;
+
+// Renders the full sphere of directions around the camera,
+// the image spans 2 * PI horizontally and PI vertically
+pub struct EquirectangularCamera<T>
+    where T: Float
+{
+    position: Vec3<T>,
+    direction: Vec3<T>,
+    lookat: Vec3<T>,
+    up: Vec3<T>,
+    u: Vec3<T>,
+    v: Vec3<T>,
+    w: Vec3<T>,
+    aspect: T,
+    fov: T,
+    aperture: T,
+    focus: T,
+    lock: CameraLock
+}
+
+impl<T> EquirectangularCamera<T>
+    where T: Float
+{
+    pub fn new() -> Self {
+        let mut camera = EquirectangularCamera {
+            position: Vec3::<T>::from_array([T::zero(), T::zero(), T::zero()]),
+            direction: Vec3::<T>::from_array([T::zero(), T::zero(), -T::one()]),
+            lookat: Vec3::<T>::from_array([T::zero(), T::zero(), -T::one()]),
+            up: Vec3::<T>::from_array([T::zero(), T::one(), T::zero()]),
+            u: Vec3::<T>::new(),
+            v: Vec3::<T>::new(),
+            w: Vec3::<T>::new(),
+            aspect: T::from(2.0).unwrap(),
+            fov: T::from(std::f64::consts::PI).unwrap(),
+            aperture: T::zero(),
+            focus: T::one(),
+            lock: CameraLock::Direction
+        };
+        camera.update();
+        camera
+    }
+
+    pub fn update(&mut self) {
+        let direction = match self.lock {
+            CameraLock::Direction => {
+                self.get_direction() * T::one()
+            },
+            CameraLock::LookAt => {
+                self.get_lookat() - self.get_position()
+            }
+        };
+        let (u, v, w) = orthonormal_basis(&direction, &self.up);
+        self.u = u;
+        self.v = v;
+        self.w = w;
+    }
+}
+
+impl<T> Camera<T> for EquirectangularCamera<T>
+    where T: Float
+{
+    fn get_position(&self) -> &Vec3<T> {
+        &self.position
+    }
+
+    fn set_position(&mut self, position: &[T]) {
+        self.position.set_data(position);
+        self.update();
+    }
+
+    fn get_direction(&self) -> &Vec3<T> {
+        &self.direction
+    }
+
+    fn set_direction(&mut self, direction: &[T]) {
+        self.direction.set_data(direction);
+        self.lock = CameraLock::Direction;
+        self.update();
+    }
+
+    fn get_lookat(&self) -> &Vec3<T> {
+        &self.lookat
+    }
+
+    fn set_lookat(&mut self, lookat: &[T]) {
+        self.lookat.set_data(lookat);
+        self.lock = CameraLock::LookAt;
+        self.update();
+    }
+
+    fn get_up(&self) -> &Vec3<T> {
+        &self.up
+    }
+
+    fn set_up(&mut self, up: &[T]) {
+        self.up.set_data(up);
+        self.update();
+    }
+
+    // Aperture, focus, aspect and fov are stored for completeness,
+    // but the panorama always covers every direction
+
+    fn get_aperture(&self) -> T {
+        self.aperture
+    }
+
+    fn set_aperture(&mut self, aperture: T) {
+        self.aperture = aperture;
+    }
+
+    fn get_focus(&self) -> T {
+        self.focus
+    }
+
+    fn set_focus(&mut self, focus: T) {
+        self.focus = focus;
+    }
+
+    fn get_aspect(&self) -> T {
+        self.aspect
+    }
+
+    fn set_aspect(&mut self, aspect: T) {
+        self.aspect = aspect;
+    }
+
+    fn get_fov(&self) -> T {
+        self.fov
+    }
+
+    fn set_fov(&mut self, fov: T) {
+        self.fov = fov;
+    }
+
+    fn get_ray(&self, r: T, s: T) -> Ray<T> {
+        let pi = T::from(std::f64::consts::PI).unwrap();
+        let half = T::from(0.5).unwrap();
+        let longitude = r * pi;
+        let latitude = s * half * pi;
+        let horizontal = &self.u * longitude.sin() + &self.w * longitude.cos();
+        let ray_direction = horizontal * latitude.cos() + &self.v * latitude.sin();
+        let origin = &self.position * T::one();
+        Ray::<T>::from_vec(origin, ray_direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &[f64], b: &[f64]) {
+        for i in 0..3 {
+            assert!((a[i] - b[i]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn rays() {
+        let mut camera = EquirectangularCamera::<f64>::new();
+        camera.set_position(&[0.0, 0.0, 1.0]);
+        camera.set_direction(&[0.0, 1.0, 0.0]);
+        camera.set_up(&[0.0, 0.0, 1.0]);
+
+        let ray = camera.get_ray(0.0, 0.0);
+        assert_eq!(ray.get_origin().get_data(), [0.0, 0.0, 1.0]);
+        assert_close(ray.get_direction().get_data(), &[0.0, 1.0, 0.0]);
+
+        // Left and right edges both look backwards
+        let ray = camera.get_ray(1.0, 0.0);
+        assert_close(ray.get_direction().get_data(), &[0.0, -1.0, 0.0]);
+        let ray = camera.get_ray(-1.0, 0.0);
+        assert_close(ray.get_direction().get_data(), &[0.0, -1.0, 0.0]);
+
+        let ray = camera.get_ray(0.5, 0.0);
+        assert_close(ray.get_direction().get_data(), &[1.0, 0.0, 0.0]);
+
+        // Top and bottom edges are the poles
+        let ray = camera.get_ray(0.3, -1.0);
+        assert_close(ray.get_direction().get_data(), &[0.0, 0.0, 1.0]);
+        let ray = camera.get_ray(-0.7, 1.0);
+        assert_close(ray.get_direction().get_data(), &[0.0, 0.0, -1.0]);
+    }
+}
@@ -0,0 +1,257 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::camera::{Camera, CameraLock};
+use crate::utils::orthonormal_basis;
+
+#[derive(Copy, Clone)]
+pub enum FisheyeProjection {
+    Equidistant, // The distance from the image center is proportional to the angle
+    Equisolid // The distance from the image center is proportional to sin(angle / 2)
+}
+
+pub struct FisheyeCamera<T>
+    where T: Float
+{
+    position: Vec3<T>,
+    direction: Vec3<T>,
+    lookat: Vec3<T>,
+    up: Vec3<T>,
+    u: Vec3<T>,
+    v: Vec3<T>,
+    w: Vec3<T>,
+    aspect: T,
+    fov: T, // The vertical field of view, can be larger than PI
+    aperture: T,
+    focus: T,
+    projection: FisheyeProjection,
+    lock: CameraLock
+}
+
+impl<T> FisheyeCamera<T>
+    where T: Float
+{
+    pub fn new() -> Self {
+        let mut camera = FisheyeCamera {
+            position: Vec3::<T>::from_array([T::zero(), T::zero(), T::zero()]),
+            direction: Vec3::<T>::from_array([T::zero(), T::zero(), -T::one()]),
+            lookat: Vec3::<T>::from_array([T::zero(), T::zero(), -T::one()]),
+            up: Vec3::<T>::from_array([T::zero(), T::one(), T::zero()]),
+            u: Vec3::<T>::new(),
+            v: Vec3::<T>::new(),
+            w: Vec3::<T>::new(),
+            aspect: T::one(),
+            fov: T::from(std::f64::consts::PI).unwrap(),
+            aperture: T::zero(),
+            focus: T::one(),
+            projection: FisheyeProjection::Equidistant,
+            lock: CameraLock::Direction
+        };
+        camera.update();
+        camera
+    }
+
+    pub fn update(&mut self) {
+        let direction = match self.lock {
+            CameraLock::Direction => {
+                self.get_direction() * T::one()
+            },
+            CameraLock::LookAt => {
+                self.get_lookat() - self.get_position()
+            }
+        };
+        let (u, v, w) = orthonormal_basis(&direction, &self.up);
+        self.u = u;
+        self.v = v;
+        self.w = w;
+    }
+
+    pub fn get_projection(&self) -> FisheyeProjection {
+        self.projection
+    }
+
+    pub fn set_projection(&mut self, projection: FisheyeProjection) {
+        self.projection = projection;
+    }
+
+    fn get_angle(&self, radius: T) -> T {
+        // The angle between the ray and the view direction,
+        // radius is 1 at the top and bottom edges of the image
+        let half = T::from(0.5).unwrap();
+        let angle = match self.projection {
+            FisheyeProjection::Equidistant => {
+                radius * half * self.fov
+            },
+            FisheyeProjection::Equisolid => {
+                let two = T::from(2.0).unwrap();
+                let s = radius * (half * half * self.fov).sin();
+                let s = if s > T::one() { T::one() } else { s };
+                two * s.asin()
+            }
+        };
+        // Outside the image circle (e.g. in the corners) the rim is repeated,
+        // instead of going past the field of view or wrapping around the back
+        let max_angle = (half * self.fov).min(T::from(std::f64::consts::PI).unwrap());
+        angle.min(max_angle)
+    }
+}
+
+impl<T> Camera<T> for FisheyeCamera<T>
+    where T: Float
+{
+    fn get_position(&self) -> &Vec3<T> {
+        &self.position
+    }
+
+    fn set_position(&mut self, position: &[T]) {
+        self.position.set_data(position);
+        self.update();
+    }
+
+    fn get_direction(&self) -> &Vec3<T> {
+        &self.direction
+    }
+
+    fn set_direction(&mut self, direction: &[T]) {
+        self.direction.set_data(direction);
+        self.lock = CameraLock::Direction;
+        self.update();
+    }
+
+    fn get_lookat(&self) -> &Vec3<T> {
+        &self.lookat
+    }
+
+    fn set_lookat(&mut self, lookat: &[T]) {
+        self.lookat.set_data(lookat);
+        self.lock = CameraLock::LookAt;
+        self.update();
+    }
+
+    fn get_up(&self) -> &Vec3<T> {
+        &self.up
+    }
+
+    fn set_up(&mut self, up: &[T]) {
+        self.up.set_data(up);
+        self.update();
+    }
+
+    fn get_aperture(&self) -> T {
+        self.aperture
+    }
+
+    fn set_aperture(&mut self, aperture: T) {
+        // Depth of field is not simulated by the fisheye camera
+        self.aperture = aperture;
+    }
+
+    fn get_focus(&self) -> T {
+        self.focus
+    }
+
+    fn set_focus(&mut self, focus: T) {
+        self.focus = focus;
+    }
+
+    fn get_aspect(&self) -> T {
+        self.aspect
+    }
+
+    fn set_aspect(&mut self, aspect: T) {
+        self.aspect = aspect;
+    }
+
+    fn get_fov(&self) -> T {
+        self.fov
+    }
+
+    fn set_fov(&mut self, fov: T) {
+        self.fov = fov;
+    }
+
+    fn get_ray(&self, r: T, s: T) -> Ray<T> {
+        let x = r * self.aspect;
+        let y = s;
+        let radius = (x * x + y * y).sqrt();
+        let ray_direction = if radius > T::zero() {
+            let angle = self.get_angle(radius);
+            let (sin, cos) = (angle.sin(), angle.cos());
+            &self.u * (sin * x / radius) + &self.v * (sin * y / radius) + &self.w * cos
+        } else {
+            &self.w * T::one()
+        };
+        let origin = &self.position * T::one();
+        Ray::<T>::from_vec(origin, ray_direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &[f64], b: &[f64]) {
+        for i in 0..3 {
+            assert!((a[i] - b[i]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn equidistant() {
+        let mut camera = FisheyeCamera::<f64>::new();
+        camera.set_position(&[1.0, 2.0, 3.0]);
+        camera.set_fov(std::f64::consts::PI);
+
+        let ray = camera.get_ray(0.0, 0.0);
+        assert_eq!(ray.get_origin().get_data(), [1.0, 2.0, 3.0]);
+        assert_close(ray.get_direction().get_data(), &[0.0, 0.0, -1.0]);
+
+        // The edges of the image are at half the field of view
+        let ray = camera.get_ray(1.0, 0.0);
+        assert_close(ray.get_direction().get_data(), &[1.0, 0.0, 0.0]);
+        let ray = camera.get_ray(0.0, -1.0);
+        assert_close(ray.get_direction().get_data(), &[0.0, 1.0, 0.0]);
+
+        // Halfway to the edge is at a quarter of the field of view
+        let ray = camera.get_ray(0.5, 0.0);
+        let c = 0.5 * 2.0f64.sqrt();
+        assert_close(ray.get_direction().get_data(), &[c, 0.0, -c]);
+    }
+
+    #[test]
+    fn equisolid() {
+        let mut camera = FisheyeCamera::<f64>::new();
+        camera.set_projection(FisheyeProjection::Equisolid);
+        camera.set_fov(std::f64::consts::PI);
+
+        let ray = camera.get_ray(0.0, 0.0);
+        assert_close(ray.get_direction().get_data(), &[0.0, 0.0, -1.0]);
+
+        let ray = camera.get_ray(1.0, 0.0);
+        assert_close(ray.get_direction().get_data(), &[1.0, 0.0, 0.0]);
+
+        // Equisolid compresses the center less than equidistant
+        let ray = camera.get_ray(0.5, 0.0);
+        let angle = 2.0 * (0.5 * (0.25 * std::f64::consts::PI).sin()).asin();
+        assert_close(ray.get_direction().get_data(), &[angle.sin(), 0.0, -angle.cos()]);
+    }
+
+    #[test]
+    fn image_circle() {
+        for projection in &[FisheyeProjection::Equidistant, FisheyeProjection::Equisolid] {
+            for fov in &[0.5 * std::f64::consts::PI, std::f64::consts::PI, 1.8 * std::f64::consts::PI] {
+                let mut camera = FisheyeCamera::<f64>::new();
+                camera.set_projection(*projection);
+                camera.set_aspect(1.5);
+                camera.set_fov(*fov);
+
+                // The corners are outside the image circle
+                for (r, s) in &[(1.0, 1.0), (-1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)] {
+                    let ray = camera.get_ray(*r, *s);
+                    let angle = (-ray.get_direction().get_data()[2]).acos();
+                    assert!(angle <= 0.5 * fov + 1e-12);
+                }
+            }
+        }
+    }
+}
@@ -2,8 +2,16 @@ use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
 
+pub mod aperture;
 pub mod perspective;
-
+pub mod orthographic;
+pub mod fisheye;
+pub mod equirectangular;
+pub mod thinlens;
+pub mod rig;
+pub mod animation;
+
+#[derive(Copy, Clone)]
 pub enum CameraLock {
     Direction,
     LookAt
@@ -0,0 +1,270 @@
+use std::rc::Rc;
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::camera::{Camera, CameraLock};
+use crate::camera::aperture::{Aperture, CircularAperture};
+use crate::utils::orthonormal_basis;
+
+pub struct OrthographicCamera<T>
+    where T: Float
+{
+    position: Vec3<T>,
+    direction: Vec3<T>,
+    lookat: Vec3<T>,
+    up: Vec3<T>,
+    u: Vec3<T>,
+    v: Vec3<T>,
+    w: Vec3<T>,
+    aspect: T,
+    fov: T,
+    height: T, // The height of the view in world units
+    half_height: T,
+    half_width: T,
+    aperture: T,
+    focus: T,
+    aperture_shape: Rc<dyn Aperture<T>>,
+    lock: CameraLock
+}
+
+impl<T> OrthographicCamera<T>
+    where T: Float
+{
+    pub fn new() -> Self {
+        let mut camera = OrthographicCamera {
+            position: Vec3::<T>::from_array([T::zero(), T::zero(), T::zero()]),
+            direction: Vec3::<T>::from_array([T::zero(), T::zero(), -T::one()]),
+            lookat: Vec3::<T>::from_array([T::zero(), T::zero(), -T::one()]),
+            up: Vec3::<T>::from_array([T::zero(), T::one(), T::zero()]),
+            u: Vec3::<T>::new(),
+            v: Vec3::<T>::new(),
+            w: Vec3::<T>::new(),
+            aspect: T::one(),
+            fov: T::from(0.5 * std::f64::consts::PI).unwrap(),
+            height: T::from(2.0).unwrap(),
+            half_height: T::one(),
+            half_width: T::one(),
+            aperture: T::zero(),
+            focus: T::one(),
+            aperture_shape: Rc::new(CircularAperture::new()),
+            lock: CameraLock::Direction
+        };
+        camera.update();
+        camera
+    }
+
+    pub fn update(&mut self) {
+        let direction = match self.lock {
+            CameraLock::Direction => {
+                self.get_direction() * T::one()
+            },
+            CameraLock::LookAt => {
+                self.get_lookat() - self.get_position()
+            }
+        };
+        let (u, v, w) = orthonormal_basis(&direction, &self.up);
+        self.u = u;
+        self.v = v;
+        self.w = w;
+        self.half_height = T::from(0.5).unwrap() * self.height;
+        self.half_width = self.aspect * self.half_height;
+    }
+
+    pub fn get_view_height(&self) -> T {
+        self.height
+    }
+
+    pub fn set_view_height(&mut self, height: T) {
+        self.height = height;
+        self.update();
+    }
+
+    pub fn get_view_width(&self) -> T {
+        self.aspect * self.height
+    }
+
+    pub fn set_view_width(&mut self, width: T) {
+        // The width is tied to the height through the aspect ratio
+        self.height = width / self.aspect;
+        self.update();
+    }
+
+    pub fn get_aperture_shape(&self) -> &Rc<dyn Aperture<T>> {
+        &self.aperture_shape
+    }
+
+    pub fn set_aperture_shape(&mut self, aperture_shape: Rc<dyn Aperture<T>>) {
+        self.aperture_shape = aperture_shape;
+    }
+}
+
+impl<T> Camera<T> for OrthographicCamera<T>
+    where T: Float
+{
+    fn get_position(&self) -> &Vec3<T> {
+        &self.position
+    }
+
+    fn set_position(&mut self, position: &[T]) {
+        self.position.set_data(position);
+        self.update();
+    }
+
+    fn get_direction(&self) -> &Vec3<T> {
+        &self.direction
+    }
+
+    fn set_direction(&mut self, direction: &[T]) {
+        self.direction.set_data(direction);
+        self.lock = CameraLock::Direction;
+        self.update();
+    }
+
+    fn get_lookat(&self) -> &Vec3<T> {
+        &self.lookat
+    }
+
+    fn set_lookat(&mut self, lookat: &[T]) {
+        self.lookat.set_data(lookat);
+        self.lock = CameraLock::LookAt;
+        self.update();
+    }
+
+    fn get_up(&self) -> &Vec3<T> {
+        &self.up
+    }
+
+    fn set_up(&mut self, up: &[T]) {
+        self.up.set_data(up);
+        self.update();
+    }
+
+    fn get_aperture(&self) -> T {
+        self.aperture
+    }
+
+    fn set_aperture(&mut self, aperture: T) {
+        self.aperture = aperture;
+        self.update();
+    }
+
+    fn get_focus(&self) -> T {
+        self.focus
+    }
+
+    fn set_focus(&mut self, focus: T) {
+        self.focus = focus;
+        self.update();
+    }
+
+    fn get_aspect(&self) -> T {
+        self.aspect
+    }
+
+    fn set_aspect(&mut self, aspect: T) {
+        self.aspect = aspect;
+        self.update();
+    }
+
+    fn get_fov(&self) -> T {
+        self.fov
+    }
+
+    fn set_fov(&mut self, fov: T) {
+        // The field of view has no effect on a parallel projection,
+        // the extent of the view is controlled by the view height instead
+        self.fov = fov;
+    }
+
+    fn get_ray(&self, r: T, s: T) -> Ray<T> {
+        let origin = &self.position + &self.u * r * self.half_width + &self.v * s * self.half_height;
+        if self.aperture > T::zero() {
+            // All the rays leaving the lens converge on the same point of the focus plane
+            let target = &origin + &self.w * self.focus;
+            let radius = self.aperture * T::from(0.5).unwrap();
+            let (x, y) = self.aperture_shape.sample();
+            let offset = &self.u * (x * radius) + &self.v * (y * radius);
+            let origin = origin + offset;
+            let mut ray_direction = target - &origin;
+            ray_direction.normalize();
+            Ray::<T>::from_vec(origin, ray_direction)
+        } else {
+            let ray_direction = &self.w * T::one();
+            Ray::<T>::from_vec(origin, ray_direction)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_camera<T>(camera: &OrthographicCamera<T>)
+        where T: Float + std::fmt::Debug
+    {
+        assert_eq!(camera.u.norm(), T::one());
+        assert_eq!(camera.v.norm(), T::one());
+        assert_eq!(camera.w.norm(), T::one());
+        assert_eq!(camera.u.dot(&camera.v), T::zero());
+        assert_eq!(camera.u.dot(&camera.w), T::zero());
+        assert_eq!(camera.v.dot(&camera.w), T::zero());
+    }
+
+    #[test]
+    fn init() {
+        let camera = OrthographicCamera::<f64>::new();
+        check_camera(&camera);
+        assert_eq!(camera.get_view_height(), 2.0);
+        assert_eq!(camera.get_view_width(), 2.0);
+    }
+
+    #[test]
+    fn set() {
+        let mut camera = OrthographicCamera::<f64>::new();
+        let position = [-2.0, 0.0, 0.0];
+        camera.set_position(&position);
+        assert_eq!(camera.get_position().get_data(), position);
+        check_camera(&camera);
+
+        let lookat = [2.0, 0.0, 0.0];
+        camera.set_lookat(&lookat);
+        assert_eq!(camera.get_lookat().get_data(), lookat);
+        check_camera(&camera);
+
+        let up = [0.0, 0.0, 1.0];
+        camera.set_up(&up);
+        check_camera(&camera);
+
+        assert_eq!(camera.w.get_data(), [1.0, 0.0, 0.0]);
+        assert_eq!(camera.u.get_data(), [0.0, -1.0, 0.0]);
+        assert_eq!(camera.v.get_data(), [0.0, 0.0, -1.0]);
+
+        camera.set_aspect(2.0);
+        camera.set_view_width(8.0);
+        assert_eq!(camera.get_view_height(), 4.0);
+        camera.set_view_height(3.0);
+        assert_eq!(camera.get_view_width(), 6.0);
+    }
+
+    #[test]
+    fn rays() {
+        let mut camera = OrthographicCamera::<f64>::new();
+        camera.set_aspect(2.0);
+        camera.set_view_height(4.0);
+        camera.set_position(&[0., 0., 10.]);
+
+        // All rays are parallel to the view direction
+        let ray = camera.get_ray(0.0, 0.0);
+        assert_eq!(ray.get_origin().get_data(), [0.0, 0.0, 10.0]);
+        assert_eq!(ray.get_direction().get_data(), [0.0, 0.0, -1.0]);
+
+        let ray = camera.get_ray(1.0, 1.0);
+        assert_eq!(ray.get_origin().get_data(), [4.0, -2.0, 10.0]);
+        assert_eq!(ray.get_direction().get_data(), [0.0, 0.0, -1.0]);
+
+        let ray = camera.get_ray(-1.0, 0.5);
+        assert_eq!(ray.get_origin().get_data(), [-4.0, -1.0, 10.0]);
+        assert_eq!(ray.get_direction().get_data(), [0.0, 0.0, -1.0]);
+    }
+}
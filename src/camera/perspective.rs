@@ -1,9 +1,13 @@
+use std::rc::Rc;
+
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::camera::{Camera, CameraLock};
-use crate::utils::random_point_in_circle;
+use crate::camera::aperture::{Aperture, CircularAperture};
+use crate::utils::orthonormal_basis;
 
+#[derive(Clone)]
 pub struct PerspectiveCamera<T>
     where T: Float
 {
@@ -19,14 +23,20 @@ pub struct PerspectiveCamera<T>
     fov: T,
     half_height: T,
     half_width: T,
+    shift_x: T, // Lens shift, as an offset of the image center at unit distance
+    shift_y: T,
     aperture: T,
     focus: T,
+    aperture_shape: Rc<dyn Aperture<T>>,
+    vignetting: T, // How much the lens barrel clips the aperture away from the image center
     lock: CameraLock
 }
 
 impl<T> PerspectiveCamera<T>
     where T: Float
 {
+    const MAX_LENS_ATTEMPTS: usize = 64;
+
     pub fn new() -> Self {
         let mut camera = PerspectiveCamera {
             position: Vec3::<T>::from_array([T::zero(), T::zero(), T::zero()]),
@@ -40,9 +50,13 @@ impl<T> PerspectiveCamera<T>
             aspect: T::one(),
             half_height: T::one(),
             half_width: T::one(),
+            shift_x: T::zero(),
+            shift_y: T::zero(),
             aperture: T::zero(),
             focus: T::one(),
             fov: T::from(0.5 * 3.1415).unwrap(),
+            aperture_shape: Rc::new(CircularAperture::new()),
+            vignetting: T::zero(),
             lock: CameraLock::Direction
         };
         camera.update();
@@ -58,16 +72,78 @@ impl<T> PerspectiveCamera<T>
                 self.get_lookat() - self.get_position()
             }
         };
-        self.w.set_data(direction.get_data());
-        self.w.normalize();
-        self.u.set_data(self.w.cross(&self.up).get_data());
-        self.u.normalize();
-        self.v.set_data(self.w.cross(&self.u).get_data());
-        self.v.normalize();
-        self.center = &self.position + &self.w * self.focus;
+        let (u, v, w) = orthonormal_basis(&direction, &self.up);
+        self.u = u;
+        self.v = v;
+        self.w = w;
+        let shift = &self.u * self.shift_x + &self.v * self.shift_y;
+        self.center = &self.position + (&self.w + shift) * self.focus;
         self.half_height = ( T::from(0.5).unwrap() * self.fov ).tan() * self.focus;
         self.half_width = self.aspect * self.half_height;
     }
+
+    pub fn get_basis(&self) -> (&Vec3<T>, &Vec3<T>, &Vec3<T>) {
+        (&self.u, &self.v, &self.w)
+    }
+
+    pub fn get_shift(&self) -> (T, T) {
+        (self.shift_x, self.shift_y)
+    }
+
+    pub fn set_shift(&mut self, shift_x: T, shift_y: T) {
+        // Shifting the lens moves the frustum without rotating the camera,
+        // i.e. the image plane stays perpendicular to the view direction
+        self.shift_x = shift_x;
+        self.shift_y = shift_y;
+        self.update();
+    }
+
+    pub fn get_aperture_shape(&self) -> &Rc<dyn Aperture<T>> {
+        &self.aperture_shape
+    }
+
+    pub fn set_aperture_shape(&mut self, aperture_shape: Rc<dyn Aperture<T>>) {
+        self.aperture_shape = aperture_shape;
+    }
+
+    pub fn get_vignetting(&self) -> T {
+        self.vignetting
+    }
+
+    pub fn set_vignetting(&mut self, vignetting: T) {
+        self.vignetting = vignetting;
+    }
+
+    fn sample_lens(&self, r: T, s: T) -> Vec3<T> {
+        // Optical vignetting: the lens barrel is modeled as a second circular opening,
+        // shifted proportionally to the distance from the image center.
+        // Its intersection with the aperture produces cat's eye shaped bokeh.
+        let shift_x = self.vignetting * r;
+        let shift_y = self.vignetting * s;
+        let (x, y) = (0..Self::MAX_LENS_ATTEMPTS)
+            .map(|_| self.aperture_shape.sample())
+            .find(|&(x, y)| {
+                let dx = x - shift_x;
+                let dy = y - shift_y;
+                dx * dx + dy * dy <= T::one()
+            })
+            .unwrap_or_else(|| Self::get_barrel_fallback(shift_x, shift_y));
+        let radius = self.aperture * T::from(0.5).unwrap();
+        &self.u * (x * radius) + &self.v * (y * radius)
+    }
+
+    // Used when no aperture sample made it through the barrel: the point of the barrel
+    // closest to the lens center, which is in the overlap of the barrel and the unit circle
+    // whenever they overlap. Otherwise the lens is fully blocked, behave like a pinhole.
+    fn get_barrel_fallback(shift_x: T, shift_y: T) -> (T, T) {
+        let distance = (shift_x * shift_x + shift_y * shift_y).sqrt();
+        if distance > T::one() && distance <= T::from(2.0).unwrap() {
+            let scale = (distance - T::one()) / distance;
+            (shift_x * scale, shift_y * scale)
+        } else {
+            (T::zero(), T::zero())
+        }
+    }
 }
 
 impl<T> Camera<T> for PerspectiveCamera<T>
@@ -79,6 +155,7 @@ impl<T> Camera<T> for PerspectiveCamera<T>
 
     fn set_position(&mut self, position: &[T]) {
         self.position.set_data(position);
+        self.update();
     }
 
     fn get_direction(&self) -> &Vec3<T> {
@@ -148,7 +225,7 @@ impl<T> Camera<T> for PerspectiveCamera<T>
 
     fn get_ray(&self, r: T, s: T) -> Ray<T> {
         let offset = if self.aperture > T::zero() {
-            random_point_in_circle(self.aperture * T::from(0.5).unwrap())
+            self.sample_lens(r, s)
         } else {
             Vec3::<T>::new()
         };
@@ -162,6 +239,7 @@ impl<T> Camera<T> for PerspectiveCamera<T>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::camera::aperture::{PolygonAperture, MaskAperture};
 
     fn check_camera<T>(camera: &PerspectiveCamera<T>)
         where T: Float + std::fmt::Debug
@@ -218,4 +296,59 @@ mod tests {
         assert_eq!(ray.get_origin().get_data(), camera.get_position().get_data());
         let ray = camera.get_ray(-1.0, 0.0);
     }
+
+    #[test]
+    fn moved() {
+        // Moving the camera keeps its direction, the image center follows the position
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[3., 2., 1.]);
+        let ray = camera.get_ray(0.0, 0.0);
+        assert_eq!(ray.get_origin().get_data(), [3., 2., 1.]);
+        assert_eq!(ray.get_direction().get_data(), [0., 0., -1.]);
+    }
+
+    #[test]
+    fn lens() {
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0., -10., 0.]);
+        camera.set_direction(&[0., 1., 0.]);
+        camera.set_up(&[0., 0., 1.]);
+        camera.set_focus(10.0);
+        camera.set_aperture(2.0);
+        camera.set_aperture_shape(Rc::new(PolygonAperture::new(6, 0.0)));
+        camera.set_vignetting(0.5);
+
+        for _ in 0..100 {
+            // The lens samples lie in the plane perpendicular to the view direction
+            let ray = camera.get_ray(0.5, -0.5);
+            let offset = ray.get_origin() - camera.get_position();
+            assert!(offset.get_data()[1].abs() < 1e-12);
+            assert!(offset.norm() <= 1.0 + 1e-12);
+
+            // All the rays through the lens converge on the focus plane
+            let ray = camera.get_ray(0.0, 0.0);
+            let t = 10.0 / ray.get_direction().get_data()[1];
+            let point = ray.get_point(t);
+            assert!(point.get_data()[0].abs() < 1e-9);
+            assert!(point.get_data()[2].abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn blocked_lens() {
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_aperture(2.0);
+        // Only the top right quadrant of the aperture is open, the barrel is shifted away from it
+        camera.set_aperture_shape(Rc::new(MaskAperture::new(2, 2, vec![0.0, 0.0, 0.0, 1.0])));
+        camera.set_vignetting(1.2);
+
+        // The barrel still overlaps the unit circle, the sample is taken in the overlap
+        let ray = camera.get_ray(-1.0, 0.0);
+        let offset = ray.get_origin() - camera.get_position();
+        assert!((offset.norm() - 0.2).abs() < 1e-12);
+
+        // The barrel doesn't overlap the unit circle at all, the lens center is used
+        let ray = camera.get_ray(-2.0, -2.0);
+        assert_eq!(ray.get_origin().get_data(), camera.get_position().get_data());
+    }
 }
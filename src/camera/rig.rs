@@ -0,0 +1,163 @@
+use crate::float::Float;
+use crate::camera::Camera;
+use crate::camera::perspective::PerspectiveCamera;
+
+#[derive(Copy, Clone)]
+pub enum Convergence {
+    ToeIn, // Each view is rotated to look at the convergence point
+    OffAxis // Views stay parallel, and their frustums are shifted to converge
+}
+
+// Derives a row of views (e.g. a stereo pair) from a single base camera.
+// The views are spread along the horizontal axis of the base camera,
+// ordered from left to right.
+pub struct CameraRig<T>
+    where T: Float
+{
+    camera: PerspectiveCamera<T>,
+    views: usize,
+    interocular: T,
+    convergence_distance: T,
+    convergence: Convergence
+}
+
+impl<T> CameraRig<T>
+    where T: Float
+{
+    pub fn new(camera: PerspectiveCamera<T>, views: usize) -> Self {
+        assert!(views > 0, "A camera rig needs at least one view");
+        CameraRig {
+            camera,
+            views,
+            interocular: T::from(0.065).unwrap(),
+            convergence_distance: T::one(),
+            convergence: Convergence::OffAxis
+        }
+    }
+
+    pub fn stereo(camera: PerspectiveCamera<T>) -> Self {
+        CameraRig::new(camera, 2)
+    }
+
+    pub fn get_camera(&self) -> &PerspectiveCamera<T> {
+        &self.camera
+    }
+
+    pub fn get_camera_mut(&mut self) -> &mut PerspectiveCamera<T> {
+        &mut self.camera
+    }
+
+    pub fn get_views(&self) -> usize {
+        self.views
+    }
+
+    pub fn get_interocular(&self) -> T {
+        self.interocular
+    }
+
+    pub fn set_interocular(&mut self, interocular: T) {
+        self.interocular = interocular;
+    }
+
+    pub fn get_convergence_distance(&self) -> T {
+        self.convergence_distance
+    }
+
+    pub fn set_convergence_distance(&mut self, convergence_distance: T) {
+        self.convergence_distance = convergence_distance;
+    }
+
+    pub fn get_convergence(&self) -> Convergence {
+        self.convergence
+    }
+
+    pub fn set_convergence(&mut self, convergence: Convergence) {
+        self.convergence = convergence;
+    }
+
+    pub fn get_cameras(&self) -> Vec<PerspectiveCamera<T>> {
+        let (u, _v, w) = self.camera.get_basis();
+        let (shift_x, shift_y) = self.camera.get_shift();
+        let position = self.camera.get_position();
+        let target = position + w * self.convergence_distance;
+        let half = T::from(0.5).unwrap();
+        let center = T::from(self.views - 1).unwrap() * half;
+
+        let mut cameras = Vec::with_capacity(self.views);
+        for i in 0..self.views {
+            let offset = (T::from(i).unwrap() - center) * self.interocular;
+            let mut camera = self.camera.clone();
+            let view_position = position + u * offset;
+            camera.set_position(view_position.get_data());
+            match self.convergence {
+                Convergence::ToeIn => {
+                    camera.set_lookat(target.get_data());
+                },
+                Convergence::OffAxis => {
+                    camera.set_direction(w.get_data());
+                    camera.set_shift(shift_x - offset / self.convergence_distance, shift_y);
+                }
+            }
+            cameras.push(camera);
+        }
+        cameras
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Vec3;
+
+    fn base_camera() -> PerspectiveCamera<f64> {
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0.0, -10.0, 0.0]);
+        camera.set_lookat(&[0.0, 0.0, 0.0]);
+        camera.set_up(&[0.0, 0.0, 1.0]);
+        camera
+    }
+
+    fn center_hit(camera: &PerspectiveCamera<f64>, distance: f64) -> Vec3<f64> {
+        // Where the central ray of the camera crosses the plane y = distance - 10
+        let ray = camera.get_ray(0.0, 0.0);
+        let t = distance / ray.get_direction().get_data()[1];
+        ray.get_point(t)
+    }
+
+    #[test]
+    fn positions() {
+        let mut rig = CameraRig::stereo(base_camera());
+        rig.set_interocular(0.5);
+        let cameras = rig.get_cameras();
+        assert_eq!(cameras.len(), 2);
+        assert_eq!(cameras[0].get_position().get_data(), [-0.25, -10.0, 0.0]);
+        assert_eq!(cameras[1].get_position().get_data(), [0.25, -10.0, 0.0]);
+
+        let rig = CameraRig::new(base_camera(), 3);
+        let cameras = rig.get_cameras();
+        assert_eq!(cameras[1].get_position().get_data(), [0.0, -10.0, 0.0]);
+    }
+
+    #[test]
+    fn convergence() {
+        let mut rig = CameraRig::stereo(base_camera());
+        rig.set_interocular(0.5);
+        rig.set_convergence_distance(5.0);
+
+        for convergence in [Convergence::ToeIn, Convergence::OffAxis].iter() {
+            rig.set_convergence(*convergence);
+            for camera in rig.get_cameras().iter() {
+                // Every view sees the convergence point at the center of the image
+                let point = center_hit(camera, 5.0);
+                assert!(point.get_data()[0].abs() < 1e-12);
+                assert!(point.get_data()[2].abs() < 1e-12);
+            }
+        }
+
+        // Off-axis views keep the image plane parallel to the base camera
+        rig.set_convergence(Convergence::OffAxis);
+        for camera in rig.get_cameras().iter() {
+            assert_eq!(camera.get_basis().2.get_data(), [0.0, 1.0, 0.0]);
+        }
+    }
+}
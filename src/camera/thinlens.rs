@@ -0,0 +1,215 @@
+use std::rc::Rc;
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::camera::Camera;
+use crate::camera::perspective::PerspectiveCamera;
+use crate::camera::aperture::Aperture;
+
+// A perspective camera described by physical lens parameters.
+// Focal length and sensor size are in millimeters, the aperture is
+// expressed as an f-number, and the scene scale converts millimeters
+// to scene units (by default the scene is assumed to be in meters).
+pub struct ThinLensCamera<T>
+    where T: Float
+{
+    camera: PerspectiveCamera<T>,
+    focal_length: T,
+    f_stop: T,
+    sensor_width: T,
+    scene_scale: T
+}
+
+impl<T> ThinLensCamera<T>
+    where T: Float
+{
+    pub fn new() -> Self {
+        let mut camera = ThinLensCamera {
+            camera: PerspectiveCamera::<T>::new(),
+            focal_length: T::from(50.0).unwrap(),
+            f_stop: T::from(8.0).unwrap(),
+            sensor_width: T::from(36.0).unwrap(),
+            scene_scale: T::from(0.001).unwrap()
+        };
+        camera.update();
+        camera
+    }
+
+    pub fn update(&mut self) {
+        let two = T::from(2.0).unwrap();
+        let fov = two * (self.get_sensor_height() / (two * self.focal_length)).atan();
+        let aperture = self.scene_scale * self.focal_length / self.f_stop;
+        self.camera.set_fov(fov);
+        self.camera.set_aperture(aperture);
+    }
+
+    pub fn get_focal_length(&self) -> T {
+        self.focal_length
+    }
+
+    pub fn set_focal_length(&mut self, focal_length: T) {
+        self.focal_length = focal_length;
+        self.update();
+    }
+
+    pub fn get_f_stop(&self) -> T {
+        self.f_stop
+    }
+
+    pub fn set_f_stop(&mut self, f_stop: T) {
+        self.f_stop = f_stop;
+        self.update();
+    }
+
+    pub fn get_sensor_width(&self) -> T {
+        self.sensor_width
+    }
+
+    pub fn set_sensor_width(&mut self, sensor_width: T) {
+        self.sensor_width = sensor_width;
+        self.update();
+    }
+
+    pub fn get_sensor_height(&self) -> T {
+        // The sensor always matches the aspect ratio of the image
+        self.sensor_width / self.camera.get_aspect()
+    }
+
+    pub fn get_aperture_shape(&self) -> &Rc<dyn Aperture<T>> {
+        self.camera.get_aperture_shape()
+    }
+
+    pub fn set_aperture_shape(&mut self, aperture_shape: Rc<dyn Aperture<T>>) {
+        self.camera.set_aperture_shape(aperture_shape);
+    }
+
+    pub fn get_vignetting(&self) -> T {
+        self.camera.get_vignetting()
+    }
+
+    pub fn set_vignetting(&mut self, vignetting: T) {
+        self.camera.set_vignetting(vignetting);
+    }
+
+    pub fn get_scene_scale(&self) -> T {
+        self.scene_scale
+    }
+
+    pub fn set_scene_scale(&mut self, scene_scale: T) {
+        self.scene_scale = scene_scale;
+        self.update();
+    }
+}
+
+impl<T> Camera<T> for ThinLensCamera<T>
+    where T: Float
+{
+    fn get_position(&self) -> &Vec3<T> {
+        self.camera.get_position()
+    }
+
+    fn set_position(&mut self, position: &[T]) {
+        self.camera.set_position(position);
+    }
+
+    fn get_direction(&self) -> &Vec3<T> {
+        self.camera.get_direction()
+    }
+
+    fn set_direction(&mut self, direction: &[T]) {
+        self.camera.set_direction(direction);
+    }
+
+    fn get_lookat(&self) -> &Vec3<T> {
+        self.camera.get_lookat()
+    }
+
+    fn set_lookat(&mut self, lookat: &[T]) {
+        self.camera.set_lookat(lookat);
+    }
+
+    fn get_up(&self) -> &Vec3<T> {
+        self.camera.get_up()
+    }
+
+    fn set_up(&mut self, up: &[T]) {
+        self.camera.set_up(up);
+    }
+
+    fn get_aperture(&self) -> T {
+        self.camera.get_aperture()
+    }
+
+    fn set_aperture(&mut self, aperture: T) {
+        // The aperture diameter is converted back to an f-number
+        self.f_stop = self.scene_scale * self.focal_length / aperture;
+        self.update();
+    }
+
+    fn get_focus(&self) -> T {
+        self.camera.get_focus()
+    }
+
+    fn set_focus(&mut self, focus: T) {
+        self.camera.set_focus(focus);
+    }
+
+    fn get_aspect(&self) -> T {
+        self.camera.get_aspect()
+    }
+
+    fn set_aspect(&mut self, aspect: T) {
+        self.camera.set_aspect(aspect);
+        self.update();
+    }
+
+    fn get_fov(&self) -> T {
+        self.camera.get_fov()
+    }
+
+    fn set_fov(&mut self, fov: T) {
+        // The field of view is converted back to a focal length
+        let two = T::from(2.0).unwrap();
+        self.focal_length = self.get_sensor_height() / (two * (fov / two).tan());
+        self.update();
+    }
+
+    fn get_ray(&self, r: T, s: T) -> Ray<T> {
+        self.camera.get_ray(r, s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn init() {
+        let camera = ThinLensCamera::<f64>::new();
+        assert_eq!(camera.get_focal_length(), 50.0);
+        assert_eq!(camera.get_f_stop(), 8.0);
+        assert_eq!(camera.get_sensor_height(), 36.0);
+        assert_eq!(camera.get_aperture(), 0.00625);
+    }
+
+    #[test]
+    fn lens() {
+        let mut camera = ThinLensCamera::<f64>::new();
+        camera.set_aspect(1.5);
+        assert_eq!(camera.get_sensor_height(), 24.0);
+
+        // A focal length equal to half the sensor height gives a 90 degrees field of view
+        camera.set_focal_length(12.0);
+        assert!((camera.get_fov() - 0.5 * std::f64::consts::PI).abs() < 1e-12);
+
+        camera.set_fov(2.0 * (0.5f64).atan());
+        assert!((camera.get_focal_length() - 24.0).abs() < 1e-12);
+
+        camera.set_f_stop(2.0);
+        assert!((camera.get_aperture() - 0.012).abs() < 1e-12);
+
+        camera.set_aperture(0.006);
+        assert!((camera.get_f_stop() - 4.0).abs() < 1e-12);
+    }
+}
@@ -3,6 +3,13 @@ use num_traits::float::{FloatCore as NumFloat};
 pub trait Number {
   fn sqrt(&self) -> Self;
   fn tan(&self) -> Self;
+  fn sin(&self) -> Self;
+  fn cos(&self) -> Self;
+  fn asin(&self) -> Self;
+  fn atan(&self) -> Self;
+  fn atan2(&self, other: Self) -> Self;
+  fn acos(&self) -> Self;
+  fn cbrt(&self) -> Self;
 }
 
 impl Number for f64 {
@@ -13,6 +20,34 @@ impl Number for f64 {
   fn tan(&self) -> Self {
     f64::tan(*self)
   }
+
+  fn sin(&self) -> Self {
+    f64::sin(*self)
+  }
+
+  fn cos(&self) -> Self {
+    f64::cos(*self)
+  }
+
+  fn asin(&self) -> Self {
+    f64::asin(*self)
+  }
+
+  fn atan(&self) -> Self {
+    f64::atan(*self)
+  }
+
+  fn atan2(&self, other: Self) -> Self {
+    f64::atan2(*self, other)
+  }
+
+  fn acos(&self) -> Self {
+    f64::acos(*self)
+  }
+
+  fn cbrt(&self) -> Self {
+    f64::cbrt(*self)
+  }
 }
 impl Number for f32 {
   fn sqrt(&self) -> Self {
@@ -22,6 +57,34 @@ impl Number for f32 {
   fn tan(&self) -> Self {
     f32::tan(*self)
   }
+
+  fn sin(&self) -> Self {
+    f32::sin(*self)
+  }
+
+  fn cos(&self) -> Self {
+    f32::cos(*self)
+  }
+
+  fn asin(&self) -> Self {
+    f32::asin(*self)
+  }
+
+  fn atan(&self) -> Self {
+    f32::atan(*self)
+  }
+
+  fn atan2(&self, other: Self) -> Self {
+    f32::atan2(*self, other)
+  }
+
+  fn acos(&self) -> Self {
+    f32::acos(*self)
+  }
+
+  fn cbrt(&self) -> Self {
+    f32::cbrt(*self)
+  }
 }
 
 pub trait Float : 'static + NumFloat + Number {}
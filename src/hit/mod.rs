@@ -3,10 +3,33 @@ use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::actor::Actor;
 
+#[derive(Clone)]
 pub struct Hit<T>
     where T: Float
 {
     pub point: Vec3<T>,
     pub normal: Vec3<T>,
-    pub t: T
+    pub tangent: Vec3<T>, // Unit vector on the surface, in the direction of increasing u
+    pub front_face: bool, // Whether the ray hit the side the normal points to
+    pub t: T,
+    pub u: T, // Surface coordinates of the hit, used to look up textures
+    pub v: T
+}
+
+impl<T> Hit<T>
+    where T: Float
+{
+    pub fn get_bitangent(&self) -> Vec3<T> {
+        // Completes the right handed (tangent, bitangent, normal) frame
+        self.normal.cross(&self.tangent)
+    }
+
+    pub fn get_shading_normal(&self) -> Vec3<T> {
+        // The normal flipped towards the incident ray, on whichever side it hit
+        if self.front_face {
+            Vec3::from_slice(self.normal.get_data())
+        } else {
+            &self.normal * (- T::one())
+        }
+    }
 }
@@ -3,6 +3,7 @@ use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::hit::Hit;
 use crate::boundingbox::BoundingBox;
+use crate::utils::step_past;
 
 pub mod primitive;
 pub mod transform;
@@ -11,6 +12,25 @@ pub trait Hitable<T>
     where T: Float
 {
     fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>>;
+    fn hit_all(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Vec<Hit<T>> {
+        // Every crossing of the surface along the ray, sorted by distance.
+        // Normals point outwards, so a hit is an entry into a closed hitable
+        // if its normal is against the ray direction, and an exit otherwise.
+        // The default walks along the ray with repeated calls to hit,
+        // hitables that know all their intersections at once should override it.
+        let mut hits = vec![];
+        let mut t_min = t_min;
+        while let Some(hit) = self.hit(ray, t_min, t_max) {
+            t_min = step_past(hit.t);
+            hits.push(hit);
+        }
+        hits
+    }
+    fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        // Whether there is any hit in the range, e.g. for shadow rays.
+        // Hitables made of several parts should override it to stop at the first hit.
+        self.hit(ray, t_min, t_max).is_some()
+    }
     fn get_bounds(&self) -> &BoundingBox<T>;
     fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>>;
     fn is_primitive(&self) -> bool {
@@ -18,4 +38,8 @@ pub trait Hitable<T>
         // Decorators (i.e. translations, rotations) return false
         true
     }
+    fn is_one_sided(&self) -> bool {
+        // Whether the hits on the back face are culled, see transform::OneSided
+        false
+    }
 }
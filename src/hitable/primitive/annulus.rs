@@ -0,0 +1,166 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::boundingbox::BoundingBox;
+use crate::utils::tangent_basis;
+
+// A flat ring centered at the origin, perpendicular to the normal
+pub struct Annulus<T>
+    where T: Float
+{
+    inner_radius: T,
+    outer_radius: T,
+    normal: Vec3<T>,
+    tangent: Vec3<T>,
+    bitangent: Vec3<T>,
+    bounds: BoundingBox<T>
+}
+
+impl<T> Annulus<T>
+    where T: Float
+{
+    pub fn new(inner_radius: T, outer_radius: T, normal: Vec3<T>) -> Self {
+        assert!(inner_radius >= T::zero() && inner_radius < outer_radius);
+        let mut normal = normal;
+        normal.normalize();
+        let (tangent, bitangent) = tangent_basis(&normal);
+        let bounds = Annulus::compute_bounds(outer_radius, &normal);
+        Annulus {
+            inner_radius,
+            outer_radius,
+            normal,
+            tangent,
+            bitangent,
+            bounds
+        }
+    }
+
+    pub fn get_inner_radius(&self) -> T {
+        self.inner_radius
+    }
+
+    pub fn get_outer_radius(&self) -> T {
+        self.outer_radius
+    }
+
+    pub fn get_normal(&self) -> &Vec3<T> {
+        &self.normal
+    }
+
+    pub fn compute_bounds(radius: T, normal: &Vec3<T>) -> BoundingBox<T> {
+        // The extent of a circle along an axis is radius * sin(angle between axis and normal)
+        let mut extent = Vec3::<T>::new();
+        for i in 0..3 {
+            let n = normal.get_data()[i];
+            let s = T::one() - n * n;
+            let s = if s > T::zero() { s.sqrt() } else { T::zero() };
+            extent.get_data_mut()[i] = radius * s;
+        }
+        BoundingBox::new(&extent * (- T::one()), extent)
+    }
+}
+
+impl<T> Hitable<T> for Annulus<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let denominator = ray.get_direction().dot(&self.normal);
+        if denominator == T::zero() {
+            return None;
+        }
+
+        let t = - ray.get_origin().dot(&self.normal) / denominator;
+        if t <= t_min || t > t_max {
+            return None;
+        }
+
+        let point = ray.get_point(t);
+        let radius = point.dot(&point).sqrt();
+        if radius < self.inner_radius || radius > self.outer_radius {
+            return None;
+        }
+
+        // Polar coordinates: u goes around the ring, v goes from the inner to the outer edge
+        let pi = T::from(std::f64::consts::PI).unwrap();
+        let angle = point.dot(&self.bitangent).atan2(point.dot(&self.tangent));
+        let u = (angle + pi) / (pi + pi);
+        let v = (radius - self.inner_radius) / (self.outer_radius - self.inner_radius);
+        let normal = &self.normal * T::one();
+        // Going around the ring, which is undefined at the center of a disk
+        let mut tangent = if radius > T::zero() { self.normal.cross(&point) } else { &self.bitangent * T::one() };
+        tangent.normalize();
+        let front_face = normal.dot(ray.get_direction()) < T::zero();
+        let hit = Hit {
+            point,
+            normal,
+            tangent,
+            front_face,
+            t,
+            u,
+            v
+        };
+
+        Some(hit)
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit() {
+        let annulus = Annulus::<f64>::new(1.0, 2.0, Vec3::from_array([0.0, 0.0, 1.0]));
+
+        let origin = [1.5, 0.0, 8.0];
+        let direction = [0.0, 0.0, -2.0];
+        let ray = Ray::from_array(origin, direction);
+        match annulus.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert_eq!(hit.point.get_data(), [1.5, 0.0, 0.0]);
+                assert_eq!(hit.normal.get_data(), [0.0, 0.0, 1.0]);
+                assert_eq!(hit.t, 4.0);
+                assert_eq!(hit.u, 0.5);
+                assert_eq!(hit.v, 0.5);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Through the hole
+        let origin = [0.5, 0.5, 8.0];
+        let ray = Ray::from_array(origin, direction);
+        assert!(annulus.hit(&ray, 0.0, 100.0).is_none());
+
+        // Outside the ring
+        let origin = [1.5, 1.5, 8.0];
+        let ray = Ray::from_array(origin, direction);
+        assert!(annulus.hit(&ray, 0.0, 100.0).is_none());
+    }
+
+    #[test]
+    fn bounds() {
+        let annulus = Annulus::<f64>::new(1.0, 2.0, Vec3::from_array([0.0, 0.0, 1.0]));
+        let bounds = annulus.get_bounds();
+        assert_eq!(bounds.get_p0().get_data(), [-2.0, -2.0, 0.0]);
+        assert_eq!(bounds.get_p1().get_data(), [2.0, 2.0, 0.0]);
+
+        let annulus = Annulus::<f64>::new(1.0, 2.0, Vec3::from_array([0.0, 1.0, 1.0]));
+        let bounds = annulus.get_bounds();
+        let c = 2.0f64.sqrt();
+        assert_eq!(bounds.get_p1().get_data()[0], 2.0);
+        assert!((bounds.get_p1().get_data()[1] - c).abs() < 1e-12);
+        assert!((bounds.get_p1().get_data()[2] - c).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::hitable::primitive::cylinder::{get_angle, hit_cap, sweep_bounds};
+use crate::boundingbox::BoundingBox;
+use crate::utils::{solve_quadratic, azimuthal_tangent};
+
+// A cone around the Z axis, centered at the origin.
+// The base is at - height / 2 and the apex at height / 2.
+// The sweep angle limits the surface to the angles in [0, sweep], starting from the X axis.
+pub struct Cone<T>
+    where T: Float
+{
+    radius: T,
+    height: T,
+    sweep: T,
+    capped: bool,
+    bounds: BoundingBox<T>
+}
+
+impl<T> Cone<T>
+    where T: Float
+{
+    pub fn new(radius: T, height: T) -> Self {
+        let mut cone = Cone {
+            radius,
+            height,
+            sweep: T::from(2.0 * std::f64::consts::PI).unwrap(),
+            capped: false,
+            bounds: BoundingBox::<T>::new(Vec3::<T>::new(), Vec3::<T>::new())
+        };
+        cone.update_bounds();
+        cone
+    }
+
+    pub fn get_radius(&self) -> T {
+        self.radius
+    }
+
+    pub fn get_height(&self) -> T {
+        self.height
+    }
+
+    pub fn get_sweep(&self) -> T {
+        self.sweep
+    }
+
+    pub fn set_sweep(&mut self, sweep: T) {
+        self.sweep = sweep;
+        self.update_bounds();
+    }
+
+    pub fn is_capped(&self) -> bool {
+        self.capped
+    }
+
+    pub fn set_capped(&mut self, capped: bool) {
+        self.capped = capped;
+    }
+
+    fn update_bounds(&mut self) {
+        // The apex is on the axis, so the center is always part of the surface
+        let half_height = T::from(0.5).unwrap() * self.height;
+        self.bounds = sweep_bounds(self.radius, self.sweep, true, - half_height, half_height);
+    }
+
+    fn hit_side(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        // x * x + y * y = k * k * (height / 2 - z) * (height / 2 - z)
+        // with the heights measured down from the apex
+        let half_height = T::from(0.5).unwrap() * self.height;
+        let k = self.radius / self.height;
+        let k2 = k * k;
+        let o = ray.get_origin().get_data();
+        let d = ray.get_direction().get_data();
+        let oz = half_height - o[2];
+        let dz = - d[2];
+        let a = d[0] * d[0] + d[1] * d[1] - k2 * dz * dz;
+        let b = T::from(2.0).unwrap() * (o[0] * d[0] + o[1] * d[1] - k2 * oz * dz);
+        let c = o[0] * o[0] + o[1] * o[1] - k2 * oz * oz;
+
+        for t in solve_quadratic(a, b, c) {
+            if t < t_min || t >= t_max {
+                continue;
+            }
+            let point = ray.get_point(t);
+            let x = point.get_data()[0];
+            let y = point.get_data()[1];
+            let z = point.get_data()[2];
+            // Discard the mirrored cone above the apex
+            if z < - half_height || z > half_height {
+                continue;
+            }
+            let phi = get_angle(&point);
+            if phi > self.sweep {
+                continue;
+            }
+            let r = k * (half_height - z);
+            let normal = if r > T::zero() {
+                let mut normal = Vec3::from_array([x, y, k * r]);
+                normal.normalize();
+                normal
+            } else {
+                Vec3::from_array([T::zero(), T::zero(), T::one()])
+            };
+            let tangent = azimuthal_tangent(&point);
+            let u = phi / self.sweep;
+            let v = (z + half_height) / self.height;
+            let front_face = normal.dot(ray.get_direction()) < T::zero();
+            return Some(Hit {
+                point,
+                normal,
+                tangent,
+                front_face,
+                t,
+                u,
+                v
+            });
+        }
+        None
+    }
+}
+
+impl<T> Hitable<T> for Cone<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let result = self.hit_side(ray, t_min, t_max);
+
+        if self.capped {
+            let t_max = match &result {
+                Some(hit) => hit.t,
+                None => t_max
+            };
+            let z = - T::from(0.5).unwrap() * self.height;
+            if let Some(hit) = hit_cap(ray, t_min, t_max, z, self.radius, self.sweep) {
+                return Some(hit);
+            }
+        }
+
+        result
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit() {
+        let mut cone = Cone::<f64>::new(2.0, 4.0);
+
+        let origin = [-8.0, 0.0, 0.0];
+        let direction = [1.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        match cone.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                let c = 1.25f64.sqrt();
+                assert_eq!(hit.point.get_data(), [-1.0, 0.0, 0.0]);
+                assert!((hit.normal.get_data()[0] + 1.0 / c).abs() < 1e-12);
+                assert!((hit.normal.get_data()[2] - 0.5 / c).abs() < 1e-12);
+                assert_eq!(hit.t, 7.0);
+                assert_eq!(hit.u, 0.5);
+                assert_eq!(hit.v, 0.5);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Above the apex, where the mirrored cone would be
+        let origin = [-8.0, 0.0, 3.0];
+        let ray = Ray::from_array(origin, direction);
+        assert!(cone.hit(&ray, 0.0, 100.0).is_none());
+
+        // From below, the open cone is hit from the inside, the capped one on its base
+        let origin = [0.5, 0.0, -8.0];
+        let direction = [0.0, 0.0, 1.0];
+        let ray = Ray::from_array(origin, direction);
+        match cone.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert!((hit.point.get_data()[2] - 1.0).abs() < 1e-12);
+                assert!((hit.t - 9.0).abs() < 1e-12);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        cone.set_capped(true);
+        match cone.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert_eq!(hit.point.get_data(), [0.5, 0.0, -2.0]);
+                assert_eq!(hit.normal.get_data(), [0.0, 0.0, -1.0]);
+                assert_eq!(hit.t, 6.0);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+    }
+
+    #[test]
+    fn bounds() {
+        let mut cone = Cone::<f64>::new(2.0, 4.0);
+        let bounds = cone.get_bounds();
+        assert_eq!(bounds.get_p0().get_data(), [-2.0, -2.0, -2.0]);
+        assert_eq!(bounds.get_p1().get_data(), [2.0, 2.0, 2.0]);
+
+        cone.set_sweep(std::f64::consts::PI);
+        let bounds = cone.get_bounds();
+        assert_eq!(bounds.get_p0().get_data()[1], 0.0);
+        assert_eq!(bounds.get_p1().get_data()[1], 2.0);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::float::Float;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::boundingbox::BoundingBox;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference // The left hitable with the right one carved out
+}
+
+impl CsgOperation {
+    fn is_inside(self, inside_left: bool, inside_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => inside_left || inside_right,
+            CsgOperation::Intersection => inside_left && inside_right,
+            CsgOperation::Difference => inside_left && !inside_right
+        }
+    }
+}
+
+// Combines two closed hitables, using the intervals where the ray is inside each of them
+pub struct Csg<T>
+    where T: Float
+{
+    operation: CsgOperation,
+    left: Box<dyn Hitable<T>>,
+    right: Box<dyn Hitable<T>>,
+    bounds: BoundingBox<T>
+}
+
+impl<T> Csg<T>
+    where T: Float
+{
+    pub fn new(operation: CsgOperation, left: Box<dyn Hitable<T>>, right: Box<dyn Hitable<T>>) -> Self {
+        let bounds = Csg::compute_bounds(operation, left.get_bounds(), right.get_bounds());
+        Csg {
+            operation,
+            left,
+            right,
+            bounds
+        }
+    }
+
+    pub fn union(left: Box<dyn Hitable<T>>, right: Box<dyn Hitable<T>>) -> Self {
+        Csg::new(CsgOperation::Union, left, right)
+    }
+
+    pub fn intersection(left: Box<dyn Hitable<T>>, right: Box<dyn Hitable<T>>) -> Self {
+        Csg::new(CsgOperation::Intersection, left, right)
+    }
+
+    pub fn difference(left: Box<dyn Hitable<T>>, right: Box<dyn Hitable<T>>) -> Self {
+        Csg::new(CsgOperation::Difference, left, right)
+    }
+
+    pub fn get_operation(&self) -> CsgOperation {
+        self.operation
+    }
+
+    fn compute_bounds(operation: CsgOperation, left: &BoundingBox<T>, right: &BoundingBox<T>) -> BoundingBox<T> {
+        let mut p0 = left.get_p0() * T::one();
+        let mut p1 = left.get_p1() * T::one();
+        match operation {
+            CsgOperation::Union => {
+                let mut bounds = BoundingBox::new(p0, p1);
+                bounds.expand(right);
+                bounds
+            },
+            CsgOperation::Intersection => {
+                for i in 0..3 {
+                    let (min, max) = right.get_axis_bounds(i);
+                    let min = if min > p0.get_data()[i] { min } else { p0.get_data()[i] };
+                    let max = if max < p1.get_data()[i] { max } else { p1.get_data()[i] };
+                    // Disjoint hitables leave an empty box at the edge of the left one
+                    let max = if max < min { min } else { max };
+                    p0.get_data_mut()[i] = min;
+                    p1.get_data_mut()[i] = max;
+                }
+                BoundingBox::new(p0, p1)
+            },
+            CsgOperation::Difference => {
+                BoundingBox::new(p0, p1)
+            }
+        }
+    }
+}
+
+impl<T> Hitable<T> for Csg<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        self.hit_all(ray, t_min, t_max).into_iter().next()
+    }
+
+    fn hit_all(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Vec<Hit<T>> {
+        // The children are intersected past t_max, so that their first hit
+        // tells whether the ray starts inside of them
+        let left = self.left.hit_all(ray, t_min, T::infinity());
+        let right = self.right.hit_all(ray, t_min, T::infinity());
+        let mut inside_left = starts_inside(&left, ray);
+        let mut inside_right = starts_inside(&right, ray);
+        let mut inside = self.operation.is_inside(inside_left, inside_right);
+
+        // Merge the hits of both children, keeping the ones where the result changes
+        let mut hits = vec![];
+        let mut left = left.into_iter().peekable();
+        let mut right = right.into_iter().peekable();
+        loop {
+            let from_left = match (left.peek(), right.peek()) {
+                (Some(l), Some(r)) => l.t <= r.t,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break
+            };
+            let mut hit = if from_left { left.next().unwrap() } else { right.next().unwrap() };
+            if hit.t >= t_max {
+                break;
+            }
+
+            let entering = is_entering(&hit, ray);
+            if from_left {
+                inside_left = entering;
+            } else {
+                inside_right = entering;
+            }
+
+            let next = self.operation.is_inside(inside_left, inside_right);
+            if next != inside {
+                inside = next;
+                // The surface of the carved out hitable is seen from the inside
+                if !from_left && self.operation == CsgOperation::Difference {
+                    hit.normal = &hit.normal * (- T::one());
+                    hit.front_face = !hit.front_face;
+                }
+                hits.push(hit);
+            }
+        }
+        hits
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+}
+
+fn is_entering<T>(hit: &Hit<T>, ray: &Ray<T>) -> bool
+    where T: Float
+{
+    hit.normal.dot(ray.get_direction()) < T::zero()
+}
+
+fn starts_inside<T>(hits: &[Hit<T>], ray: &Ray<T>) -> bool
+    where T: Float
+{
+    match hits.first() {
+        Some(hit) => !is_entering(hit, ray),
+        None => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Vec3;
+    use crate::hitable::primitive::{Sphere, Cube};
+    use crate::hitable::transform::Translation;
+
+    fn make_csg(operation: CsgOperation) -> Csg<f64> {
+        // A sphere of radius 2 and a cube covering x in [1, 3]
+        let sphere = Box::new(Sphere::new(2.0));
+        let cube = Box::new(Cube::new(2.0, 2.0, 2.0));
+        let cube = Box::new(Translation::new(cube, Vec3::from_array([2.0, 0.0, 0.0])));
+        Csg::new(operation, sphere, cube)
+    }
+
+    #[test]
+    fn hit() {
+        let origin = [8.0, 0.0, 0.0];
+        let direction = [-1.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+
+        let expected = [
+            (CsgOperation::Union, [5.0, 10.0], 3.0),
+            (CsgOperation::Intersection, [6.0, 7.0], 2.0),
+            (CsgOperation::Difference, [7.0, 10.0], 1.0)
+        ];
+
+        for (operation, t, x) in expected.iter() {
+            let csg = make_csg(*operation);
+            let hits = csg.hit_all(&ray, 0.0, 100.0);
+            assert_eq!(hits.len(), 2);
+            for i in 0..2 {
+                assert!((hits[i].t - t[i]).abs() < 1e-9);
+            }
+            // Normals point out of the combined solid
+            assert_eq!(hits[0].normal.get_data(), [1.0, 0.0, 0.0]);
+            assert_eq!(hits[1].normal.get_data(), [-1.0, 0.0, 0.0]);
+
+            match csg.hit(&ray, 0.0, 100.0) {
+                Some(hit) => {
+                    assert!((hit.point.get_data()[0] - x).abs() < 1e-9);
+                },
+                None => {
+                    assert!(false);
+                }
+            }
+        }
+
+        // Missing the carved out part
+        let csg = make_csg(CsgOperation::Difference);
+        let origin = [0.0, 0.0, 8.0];
+        let direction = [0.0, 0.0, -1.0];
+        let ray = Ray::from_array(origin, direction);
+        match csg.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert_eq!(hit.t, 6.0);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Starting inside the sphere, the ray leaves it through the carved out cube
+        let origin = [0.0, 0.0, 0.0];
+        let direction = [1.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        match csg.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert!((hit.t - 1.0).abs() < 1e-9);
+                assert_eq!(hit.normal.get_data(), [1.0, 0.0, 0.0]);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Nested operations
+        let inner = Box::new(make_csg(CsgOperation::Difference));
+        let sphere = Box::new(Sphere::new(0.5));
+        let csg = Csg::difference(inner, sphere);
+        let origin = [-8.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        let hits = csg.hit_all(&ray, 0.0, 100.0);
+        assert_eq!(hits.len(), 4);
+        let expected = [6.0, 7.5, 8.5, 9.0];
+        for i in 0..4 {
+            assert!((hits[i].t - expected[i]).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn bounds() {
+        let csg = make_csg(CsgOperation::Union);
+        assert_eq!(csg.get_bounds().get_p0().get_data(), [-2.0, -2.0, -2.0]);
+        assert_eq!(csg.get_bounds().get_p1().get_data(), [3.0, 2.0, 2.0]);
+
+        let csg = make_csg(CsgOperation::Intersection);
+        assert_eq!(csg.get_bounds().get_p0().get_data(), [1.0, -1.0, -1.0]);
+        assert_eq!(csg.get_bounds().get_p1().get_data(), [2.0, 1.0, 1.0]);
+
+        let csg = make_csg(CsgOperation::Difference);
+        assert_eq!(csg.get_bounds().get_p0().get_data(), [-2.0, -2.0, -2.0]);
+        assert_eq!(csg.get_bounds().get_p1().get_data(), [2.0, 2.0, 2.0]);
+    }
+}
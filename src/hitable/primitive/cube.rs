@@ -69,6 +69,10 @@ impl<T> Hitable<T> for Cube<T>
         self.faces.hit(ray, t_min, t_max)
     }
 
+    fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        self.faces.any_hit(ray, t_min, t_max)
+    }
+
     fn get_bounds(&self) -> &BoundingBox<T> {
         self.faces.get_bounds()
     }
@@ -226,4 +230,19 @@ mod tests {
         assert_eq!(bounds.get_p0().get_data(), [-1.0, -2.0, -3.0]);
         assert_eq!(bounds.get_p1().get_data(), [1.0, 2.0, 3.0]);
     }
+
+    #[test]
+    fn any_hit() {
+        let cube = Cube::<f64>::new(2.0, 4.0, 6.0);
+
+        let origin = [-8.0, 0.0, 0.0];
+        let direction = [2.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        assert!(cube.any_hit(&ray, 0.0, 100.0));
+        assert!(!cube.any_hit(&ray, 0.0, 3.0));
+
+        let origin = [-8.0, 2.5, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        assert!(!cube.any_hit(&ray, 0.0, 100.0));
+    }
 }
@@ -0,0 +1,307 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::boundingbox::BoundingBox;
+use crate::utils::{solve_quadratic, azimuthal_tangent};
+
+// A cylinder around the Z axis, centered at the origin.
+// The sweep angle limits the surface to the angles in [0, sweep], starting from the X axis.
+pub struct Cylinder<T>
+    where T: Float
+{
+    radius: T,
+    height: T,
+    sweep: T,
+    capped: bool,
+    bounds: BoundingBox<T>
+}
+
+impl<T> Cylinder<T>
+    where T: Float
+{
+    pub fn new(radius: T, height: T) -> Self {
+        let mut cylinder = Cylinder {
+            radius,
+            height,
+            sweep: T::from(2.0 * std::f64::consts::PI).unwrap(),
+            capped: false,
+            bounds: BoundingBox::<T>::new(Vec3::<T>::new(), Vec3::<T>::new())
+        };
+        cylinder.update_bounds();
+        cylinder
+    }
+
+    pub fn get_radius(&self) -> T {
+        self.radius
+    }
+
+    pub fn get_height(&self) -> T {
+        self.height
+    }
+
+    pub fn get_sweep(&self) -> T {
+        self.sweep
+    }
+
+    pub fn set_sweep(&mut self, sweep: T) {
+        self.sweep = sweep;
+        self.update_bounds();
+    }
+
+    pub fn is_capped(&self) -> bool {
+        self.capped
+    }
+
+    pub fn set_capped(&mut self, capped: bool) {
+        self.capped = capped;
+        self.update_bounds();
+    }
+
+    fn update_bounds(&mut self) {
+        let half_height = T::from(0.5).unwrap() * self.height;
+        // The center of the caps only belongs to the surface if they are present
+        self.bounds = sweep_bounds(self.radius, self.sweep, self.capped, - half_height, half_height);
+    }
+
+    fn hit_side(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let o = ray.get_origin().get_data();
+        let d = ray.get_direction().get_data();
+        let a = d[0] * d[0] + d[1] * d[1];
+        let b = T::from(2.0).unwrap() * (o[0] * d[0] + o[1] * d[1]);
+        let c = o[0] * o[0] + o[1] * o[1] - self.radius * self.radius;
+        let half_height = T::from(0.5).unwrap() * self.height;
+
+        for t in solve_quadratic(a, b, c) {
+            if t < t_min || t >= t_max {
+                continue;
+            }
+            let point = ray.get_point(t);
+            let z = point.get_data()[2];
+            if z < - half_height || z > half_height {
+                continue;
+            }
+            let phi = get_angle(&point);
+            if phi > self.sweep {
+                continue;
+            }
+            let normal = Vec3::from_array([point.get_data()[0] / self.radius, point.get_data()[1] / self.radius, T::zero()]);
+            let tangent = azimuthal_tangent(&point);
+            let u = phi / self.sweep;
+            let v = (z + half_height) / self.height;
+            let front_face = normal.dot(ray.get_direction()) < T::zero();
+            return Some(Hit {
+                point,
+                normal,
+                tangent,
+                front_face,
+                t,
+                u,
+                v
+            });
+        }
+        None
+    }
+
+    fn hit_cap(&self, ray: &Ray<T>, t_min: T, t_max: T, z: T) -> Option<Hit<T>> {
+        hit_cap(ray, t_min, t_max, z, self.radius, self.sweep)
+    }
+}
+
+impl<T> Hitable<T> for Cylinder<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let mut t_max = t_max;
+        let mut result = self.hit_side(ray, t_min, t_max);
+
+        if self.capped {
+            let half_height = T::from(0.5).unwrap() * self.height;
+            for z in [- half_height, half_height].iter() {
+                if let Some(hit) = &result {
+                    t_max = hit.t;
+                }
+                if let Some(hit) = self.hit_cap(ray, t_min, t_max, *z) {
+                    result = Some(hit);
+                }
+            }
+        }
+
+        result
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+}
+
+// Helpers shared with the cone
+
+pub fn get_angle<T>(point: &Vec3<T>) -> T
+    where T: Float
+{
+    // The angle around the Z axis, in [0, 2 * PI)
+    let two_pi = T::from(2.0 * std::f64::consts::PI).unwrap();
+    let phi = point.get_data()[1].atan2(point.get_data()[0]);
+    if phi < T::zero() { phi + two_pi } else { phi }
+}
+
+pub fn hit_cap<T>(ray: &Ray<T>, t_min: T, t_max: T, z: T, radius: T, sweep: T) -> Option<Hit<T>>
+    where T: Float
+{
+    // A flat cap at height z, its normal points away from the center of the solid
+    let dz = ray.get_direction().get_data()[2];
+    if dz == T::zero() {
+        return None;
+    }
+    let t = (z - ray.get_origin().get_data()[2]) / dz;
+    if t < t_min || t >= t_max {
+        return None;
+    }
+    let point = ray.get_point(t);
+    let x = point.get_data()[0];
+    let y = point.get_data()[1];
+    let r = (x * x + y * y).sqrt();
+    if r > radius {
+        return None;
+    }
+    let phi = get_angle(&point);
+    if phi > sweep {
+        return None;
+    }
+    let sign = if z < T::zero() { - T::one() } else { T::one() };
+    let normal = Vec3::from_array([T::zero(), T::zero(), sign]);
+    let tangent = azimuthal_tangent(&point);
+    let u = phi / sweep;
+    let v = r / radius;
+    let front_face = normal.dot(ray.get_direction()) < T::zero();
+    Some(Hit {
+        point,
+        normal,
+        tangent,
+        front_face,
+        t,
+        u,
+        v
+    })
+}
+
+pub fn sweep_bounds<T>(radius: T, sweep: T, include_center: bool, min_z: T, max_z: T) -> BoundingBox<T>
+    where T: Float
+{
+    // The XY extent of an arc of circle: its end points,
+    // plus the points crossing the axes that are within the sweep
+    let half_pi = T::from(0.5 * std::f64::consts::PI).unwrap();
+    let mut angles = vec![T::zero(), sweep];
+    for i in 1..4 {
+        let angle = half_pi * T::from(i).unwrap();
+        if angle < sweep {
+            angles.push(angle);
+        }
+    }
+
+    let (mut min_x, mut max_x, mut min_y, mut max_y) = if include_center {
+        (T::zero(), T::zero(), T::zero(), T::zero())
+    } else {
+        (T::infinity(), T::neg_infinity(), T::infinity(), T::neg_infinity())
+    };
+    for angle in angles {
+        let x = radius * angle.cos();
+        let y = radius * angle.sin();
+        min_x = if x < min_x { x } else { min_x };
+        max_x = if x > max_x { x } else { max_x };
+        min_y = if y < min_y { y } else { min_y };
+        max_y = if y > max_y { y } else { max_y };
+    }
+
+    BoundingBox::new(
+        Vec3::from_array([min_x, min_y, min_z]),
+        Vec3::from_array([max_x, max_y, max_z])
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit() {
+        let mut cylinder = Cylinder::<f64>::new(2.0, 4.0);
+
+        let origin = [-8.0, 0.0, 1.0];
+        let direction = [2.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        match cylinder.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert_eq!(hit.point.get_data(), [-2.0, 0.0, 1.0]);
+                assert_eq!(hit.normal.get_data(), [-1.0, 0.0, 0.0]);
+                assert_eq!(hit.t, 3.0);
+                assert_eq!(hit.u, 0.5);
+                assert_eq!(hit.v, 0.75);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Above the cylinder
+        let origin = [-8.0, 0.0, 2.5];
+        let ray = Ray::from_array(origin, direction);
+        assert!(cylinder.hit(&ray, 0.0, 100.0).is_none());
+
+        // Along the axis, the open cylinder is missed, the capped one is not
+        let origin = [0.5, 0.0, 8.0];
+        let direction = [0.0, 0.0, -1.0];
+        let ray = Ray::from_array(origin, direction);
+        assert!(cylinder.hit(&ray, 0.0, 100.0).is_none());
+
+        cylinder.set_capped(true);
+        match cylinder.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert_eq!(hit.point.get_data(), [0.5, 0.0, 2.0]);
+                assert_eq!(hit.normal.get_data(), [0.0, 0.0, 1.0]);
+                assert_eq!(hit.t, 6.0);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Half a cylinder only keeps the positive Y side
+        cylinder.set_capped(false);
+        cylinder.set_sweep(std::f64::consts::PI);
+        let origin = [0.0, -8.0, 0.0];
+        let direction = [0.0, 1.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        match cylinder.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert_eq!(hit.point.get_data(), [0.0, 2.0, 0.0]);
+                assert_eq!(hit.normal.get_data(), [0.0, 1.0, 0.0]);
+                assert_eq!(hit.t, 10.0);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+    }
+
+    #[test]
+    fn bounds() {
+        let mut cylinder = Cylinder::<f64>::new(2.0, 4.0);
+        let bounds = cylinder.get_bounds();
+        assert_eq!(bounds.get_p0().get_data(), [-2.0, -2.0, -2.0]);
+        assert_eq!(bounds.get_p1().get_data(), [2.0, 2.0, 2.0]);
+
+        cylinder.set_sweep(0.5 * std::f64::consts::PI);
+        let bounds = cylinder.get_bounds();
+        assert!(bounds.get_p0().get_data()[0].abs() < 1e-12);
+        assert!(bounds.get_p0().get_data()[1].abs() < 1e-12);
+        assert_eq!(bounds.get_p1().get_data()[0], 2.0);
+        assert_eq!(bounds.get_p1().get_data()[1], 2.0);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::hitable::primitive::Annulus;
+use crate::boundingbox::BoundingBox;
+
+// A flat disk centered at the origin, perpendicular to the normal.
+// It is an annulus without a hole.
+pub struct Disk<T>
+    where T: Float
+{
+    radius: T,
+    annulus: Annulus<T>
+}
+
+impl<T> Disk<T>
+    where T: Float
+{
+    pub fn new(radius: T, normal: Vec3<T>) -> Self {
+        let annulus = Annulus::new(T::zero(), radius, normal);
+        Disk {
+            radius,
+            annulus
+        }
+    }
+
+    pub fn get_radius(&self) -> T {
+        self.radius
+    }
+
+    pub fn get_normal(&self) -> &Vec3<T> {
+        self.annulus.get_normal()
+    }
+}
+
+impl<T> Hitable<T> for Disk<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        self.annulus.hit(ray, t_min, t_max)
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        self.annulus.get_bounds()
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit() {
+        let disk = Disk::<f64>::new(2.0, Vec3::from_array([1.0, 0.0, 0.0]));
+
+        let origin = [-8.0, 0.0, 0.0];
+        let direction = [2.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        match disk.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert_eq!(hit.point.get_data(), [0.0, 0.0, 0.0]);
+                assert_eq!(hit.normal.get_data(), [1.0, 0.0, 0.0]);
+                assert_eq!(hit.t, 4.0);
+                assert_eq!(hit.v, 0.0);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        let origin = [-8.0, 1.5, 1.5];
+        let ray = Ray::from_array(origin, direction);
+        assert!(disk.hit(&ray, 0.0, 100.0).is_none());
+    }
+
+    #[test]
+    fn bounds() {
+        let disk = Disk::<f64>::new(2.0, Vec3::from_array([1.0, 0.0, 0.0]));
+        let bounds = disk.get_bounds();
+        assert_eq!(bounds.get_p0().get_data(), [0.0, -2.0, -2.0]);
+        assert_eq!(bounds.get_p1().get_data(), [0.0, 2.0, 2.0]);
+    }
+}
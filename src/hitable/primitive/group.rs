@@ -44,6 +44,10 @@ impl<T> Hitable<T> for Group<T>
         result
     }
 
+    fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        self.hitables.iter().any(|hitable| hitable.any_hit(ray, t_min, t_max))
+    }
+
     fn get_bounds(&self) -> &BoundingBox<T> {
         &self.bounds
     }
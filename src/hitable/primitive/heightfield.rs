@@ -0,0 +1,359 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::boundingbox::BoundingBox;
+
+// A terrain made of a grid of heights along the Z axis, centered at the origin in the XY plane.
+// The heights are stored row by row, rows go along Y and columns along X.
+// Each cell is split in two triangles, which are only tested for the cells the ray walks through.
+pub struct Heightfield<T>
+    where T: Float
+{
+    columns: usize,
+    rows: usize,
+    heights: Vec<T>,
+    normals: Vec<Vec3<T>>, // Per sample, interpolated across the triangles
+    spacing_x: T,
+    spacing_y: T,
+    bounds: BoundingBox<T>
+}
+
+impl<T> Heightfield<T>
+    where T: Float
+{
+    pub fn new(columns: usize, rows: usize, heights: Vec<T>, spacing_x: T, spacing_y: T) -> Self {
+        assert!(columns >= 2 && rows >= 2, "Heightfield needs at least 2x2 samples");
+        assert_eq!(heights.len(), columns * rows);
+
+        let mut min_z = T::infinity();
+        let mut max_z = T::neg_infinity();
+        for h in heights.iter() {
+            min_z = if *h < min_z { *h } else { min_z };
+            max_z = if *h > max_z { *h } else { max_z };
+        }
+        let half = T::from(0.5).unwrap();
+        let half_width = half * spacing_x * T::from(columns - 1).unwrap();
+        let half_depth = half * spacing_y * T::from(rows - 1).unwrap();
+        let bounds = BoundingBox::new(
+            Vec3::from_array([- half_width, - half_depth, min_z]),
+            Vec3::from_array([half_width, half_depth, max_z])
+        );
+
+        let mut heightfield = Heightfield {
+            columns,
+            rows,
+            heights,
+            normals: vec![],
+            spacing_x,
+            spacing_y,
+            bounds
+        };
+        heightfield.normals = heightfield.compute_normals();
+        heightfield
+    }
+
+    pub fn get_columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn get_rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn get_height(&self, column: usize, row: usize) -> T {
+        self.heights[row * self.columns + column]
+    }
+
+    fn get_vertex(&self, column: usize, row: usize) -> Vec3<T> {
+        let (x0, y0) = self.get_origin();
+        Vec3::from_array([
+            x0 + self.spacing_x * T::from(column).unwrap(),
+            y0 + self.spacing_y * T::from(row).unwrap(),
+            self.get_height(column, row)
+        ])
+    }
+
+    fn get_origin(&self) -> (T, T) {
+        (self.bounds.get_p0().get_data()[0], self.bounds.get_p0().get_data()[1])
+    }
+
+    fn compute_normals(&self) -> Vec<Vec3<T>> {
+        // Central differences, one sided at the borders
+        let mut normals = Vec::with_capacity(self.heights.len());
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let c0 = if column > 0 { column - 1 } else { column };
+                let c1 = if column + 1 < self.columns { column + 1 } else { column };
+                let r0 = if row > 0 { row - 1 } else { row };
+                let r1 = if row + 1 < self.rows { row + 1 } else { row };
+                let dx = (self.get_height(c1, row) - self.get_height(c0, row)) / (self.spacing_x * T::from(c1 - c0).unwrap());
+                let dy = (self.get_height(column, r1) - self.get_height(column, r0)) / (self.spacing_y * T::from(r1 - r0).unwrap());
+                let mut normal = Vec3::from_array([- dx, - dy, T::one()]);
+                normal.normalize();
+                normals.push(normal);
+            }
+        }
+        normals
+    }
+
+    fn get_cell_range(&self, column: usize, row: usize) -> (T, T) {
+        let heights = [
+            self.get_height(column, row),
+            self.get_height(column + 1, row),
+            self.get_height(column, row + 1),
+            self.get_height(column + 1, row + 1)
+        ];
+        let mut min = heights[0];
+        let mut max = heights[0];
+        for h in heights.iter() {
+            min = if *h < min { *h } else { min };
+            max = if *h > max { *h } else { max };
+        }
+        (min, max)
+    }
+
+    fn hit_cell(&self, ray: &Ray<T>, column: usize, row: usize, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let corners = [(column, row), (column + 1, row), (column + 1, row + 1), (column, row + 1)];
+        let triangles = [[0, 1, 2], [0, 2, 3]];
+        let mut t_max = t_max;
+        let mut result = None;
+
+        for triangle in triangles.iter() {
+            let (c0, r0) = corners[triangle[0]];
+            let (c1, r1) = corners[triangle[1]];
+            let (c2, r2) = corners[triangle[2]];
+            let v0 = self.get_vertex(c0, r0);
+            let v1 = self.get_vertex(c1, r1);
+            let v2 = self.get_vertex(c2, r2);
+
+            if let Some((t, b1, b2)) = hit_triangle(ray, &v0, &v1, &v2, t_min, t_max) {
+                let b0 = T::one() - b1 - b2;
+                let mut normal = &self.normals[r0 * self.columns + c0] * b0
+                    + &self.normals[r1 * self.columns + c1] * b1
+                    + &self.normals[r2 * self.columns + c2] * b2;
+                normal.normalize();
+
+                // u goes along X, projected on the surface
+                let mut tangent = Vec3::from_array([T::one(), T::zero(), T::zero()]);
+                tangent = &tangent - &normal * normal.get_data()[0];
+                tangent.normalize();
+
+                let point = ray.get_point(t);
+                let (x0, y0) = self.get_origin();
+                let u = (point.get_data()[0] - x0) / self.bounds.get_axis_length(0);
+                let v = (point.get_data()[1] - y0) / self.bounds.get_axis_length(1);
+                t_max = t;
+                let front_face = normal.dot(ray.get_direction()) < T::zero();
+                result = Some(Hit {
+                    point,
+                    normal,
+                    tangent,
+                    front_face,
+                    t,
+                    u,
+                    v
+                });
+            }
+        }
+
+        result
+    }
+}
+
+impl<T> Hitable<T> for Heightfield<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let (t_enter, t_exit) = self.bounds.intersect(ray, t_min, t_max)?;
+
+        // Walk the cells under the ray with a 2D DDA, in order of distance
+        let (x0, y0) = self.get_origin();
+        let origin = ray.get_origin().get_data();
+        let direction = ray.get_direction().get_data();
+        let start = ray.get_point(t_enter);
+        let spacing = [self.spacing_x, self.spacing_y];
+        let last = [self.columns - 2, self.rows - 2];
+        let offset = [x0, y0];
+
+        let mut cell = [0, 0];
+        let mut step = [0isize, 0isize];
+        let mut t_next = [T::infinity(), T::infinity()];
+        let mut t_delta = [T::infinity(), T::infinity()];
+        for i in 0..2 {
+            let position = (start.get_data()[i] - offset[i]) / spacing[i];
+            let position = T::to_isize(&position.floor()).unwrap_or(0);
+            cell[i] = if position < 0 { 0 } else if position as usize > last[i] { last[i] } else { position as usize };
+
+            if direction[i] > T::zero() {
+                step[i] = 1;
+                let boundary = offset[i] + spacing[i] * T::from(cell[i] + 1).unwrap();
+                t_next[i] = (boundary - origin[i]) / direction[i];
+                t_delta[i] = spacing[i] / direction[i];
+            } else if direction[i] < T::zero() {
+                step[i] = -1;
+                let boundary = offset[i] + spacing[i] * T::from(cell[i]).unwrap();
+                t_next[i] = (boundary - origin[i]) / direction[i];
+                t_delta[i] = - spacing[i] / direction[i];
+            }
+        }
+
+        let mut t_cell = t_enter;
+        loop {
+            let axis = if t_next[0] < t_next[1] { 0 } else { 1 };
+            let t_leave = if t_next[axis] < t_exit { t_next[axis] } else { t_exit };
+
+            // Skip the cells the ray passes entirely above or below
+            let z0 = origin[2] + t_cell * direction[2];
+            let z1 = origin[2] + t_leave * direction[2];
+            let (ray_min, ray_max) = if z0 < z1 { (z0, z1) } else { (z1, z0) };
+            let (cell_min, cell_max) = self.get_cell_range(cell[0], cell[1]);
+            if ray_max >= cell_min && ray_min <= cell_max {
+                if let Some(hit) = self.hit_cell(ray, cell[0], cell[1], t_min, t_max) {
+                    return Some(hit);
+                }
+            }
+
+            if t_leave >= t_exit {
+                return None;
+            }
+            if (step[axis] < 0 && cell[axis] == 0) || (step[axis] > 0 && cell[axis] == last[axis]) {
+                return None;
+            }
+            cell[axis] = (cell[axis] as isize + step[axis]) as usize;
+            t_cell = t_next[axis];
+            t_next[axis] = t_next[axis] + t_delta[axis];
+        }
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+}
+
+fn hit_triangle<T>(ray: &Ray<T>, v0: &Vec3<T>, v1: &Vec3<T>, v2: &Vec3<T>, t_min: T, t_max: T) -> Option<(T, T, T)>
+    where T: Float
+{
+    // Moller-Trumbore, returns the distance and the barycentric coordinates of v1 and v2
+    let edge1 = v1 - v0;
+    let edge2 = v2 - v0;
+    let p = ray.get_direction().cross(&edge2);
+    let determinant = edge1.dot(&p);
+    if determinant == T::zero() {
+        return None;
+    }
+    let inv_determinant = T::one() / determinant;
+    let s = ray.get_origin() - v0;
+    let b1 = s.dot(&p) * inv_determinant;
+    if b1 < T::zero() || b1 > T::one() {
+        return None;
+    }
+    let q = s.cross(&edge1);
+    let b2 = ray.get_direction().dot(&q) * inv_determinant;
+    if b2 < T::zero() || b1 + b2 > T::one() {
+        return None;
+    }
+    let t = edge2.dot(&q) * inv_determinant;
+    if t <= t_min || t > t_max {
+        return None;
+    }
+    Some((t, b1, b2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_ramp() -> Heightfield<f64> {
+        // 5x3 samples, 1 apart, rising by 0.5 per column
+        let mut heights = vec![];
+        for _row in 0..3 {
+            for column in 0..5 {
+                heights.push(0.5 * column as f64);
+            }
+        }
+        Heightfield::new(5, 3, heights, 1.0, 1.0)
+    }
+
+    #[test]
+    fn bounds() {
+        let heightfield = make_ramp();
+        let bounds = heightfield.get_bounds();
+        assert_eq!(bounds.get_p0().get_data(), [-2.0, -1.0, 0.0]);
+        assert_eq!(bounds.get_p1().get_data(), [2.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn hit() {
+        let heightfield = make_ramp();
+        let c = 0.8f64.sqrt();
+
+        // Straight down
+        let origin = [0.5, 0.25, 10.0];
+        let direction = [0.0, 0.0, -1.0];
+        let ray = Ray::from_array(origin, direction);
+        match heightfield.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert!((hit.point.get_data()[2] - 1.25).abs() < 1e-12);
+                assert!((hit.normal.get_data()[0] + 0.5 * c).abs() < 1e-12);
+                assert!((hit.normal.get_data()[2] - c).abs() < 1e-12);
+                assert!((hit.t - 8.75).abs() < 1e-12);
+                assert!((hit.u - 0.625).abs() < 1e-12);
+                assert!((hit.v - 0.625).abs() < 1e-12);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Skimming over the field until it rises to the ray
+        let origin = [-10.0, 0.3, 1.5];
+        let direction = [1.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        match heightfield.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert!((hit.point.get_data()[0] - 1.0).abs() < 1e-12);
+                assert!((hit.t - 11.0).abs() < 1e-12);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Along the other axis, above the field
+        let origin = [0.5, -10.0, 1.5];
+        let direction = [0.0, 1.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        assert!(heightfield.hit(&ray, 0.0, 100.0).is_none());
+
+        // Diagonally from below the ramp
+        let origin = [2.5, -2.0, 1.0];
+        let direction = [-1.0, 1.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        match heightfield.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert!((hit.point.get_data()[0]).abs() < 1e-12);
+                assert!((hit.t - 2.5).abs() < 1e-12);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+    }
+
+    #[test]
+    fn normals() {
+        // A single bump in the middle
+        let mut heights = vec![0.0; 9];
+        heights[4] = 1.0;
+        let heightfield = Heightfield::<f64>::new(3, 3, heights, 1.0, 1.0);
+        assert_eq!(heightfield.normals[4].get_data(), [0.0, 0.0, 1.0]);
+        let n = heightfield.normals[3].get_data();
+        assert!(n[0] < 0.0 && n[1] == 0.0 && n[2] > 0.0);
+    }
+}
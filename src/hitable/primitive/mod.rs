@@ -2,8 +2,28 @@ pub mod sphere;
 pub mod rectangle;
 pub mod cube;
 pub mod group;
+pub mod plane;
+pub mod annulus;
+pub mod disk;
+pub mod cylinder;
+pub mod cone;
+pub mod torus;
+pub mod quad;
+pub mod csg;
+pub mod sdf;
+pub mod heightfield;
 
 pub use sphere::Sphere;
 pub use rectangle::Rectangle;
 pub use cube::Cube;
 pub use group::Group;
+pub use plane::Plane;
+pub use annulus::Annulus;
+pub use disk::Disk;
+pub use cylinder::Cylinder;
+pub use cone::Cone;
+pub use torus::Torus;
+pub use quad::Quad;
+pub use csg::{Csg, CsgOperation};
+pub use sdf::SdfHitable;
+pub use heightfield::Heightfield;
@@ -0,0 +1,140 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::boundingbox::BoundingBox;
+use crate::utils::tangent_basis;
+
+// An infinite plane through the origin
+pub struct Plane<T>
+    where T: Float
+{
+    normal: Vec3<T>,
+    tangent: Vec3<T>,
+    bitangent: Vec3<T>,
+    bounds: BoundingBox<T>
+}
+
+impl<T> Plane<T>
+    where T: Float
+{
+    pub fn new(normal: Vec3<T>) -> Self {
+        let mut normal = normal;
+        normal.normalize();
+        let (tangent, bitangent) = tangent_basis(&normal);
+        Plane {
+            normal,
+            tangent,
+            bitangent,
+            bounds: BoundingBox::infinite()
+        }
+    }
+
+    pub fn get_normal(&self) -> &Vec3<T> {
+        &self.normal
+    }
+}
+
+impl<T> Hitable<T> for Plane<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let denominator = ray.get_direction().dot(&self.normal);
+        if denominator == T::zero() {
+            return None;
+        }
+
+        let t = - ray.get_origin().dot(&self.normal) / denominator;
+        if t <= t_min || t > t_max {
+            return None;
+        }
+
+        // The surface coordinates are the distances along the tangent and bitangent
+        let point = ray.get_point(t);
+        let u = point.dot(&self.tangent);
+        let v = point.dot(&self.bitangent);
+        let normal = &self.normal * T::one();
+        let tangent = &self.tangent * T::one();
+        let front_face = normal.dot(ray.get_direction()) < T::zero();
+        let hit = Hit {
+            point,
+            normal,
+            tangent,
+            front_face,
+            t,
+            u,
+            v
+        };
+
+        Some(hit)
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn init() {
+        let plane = Plane::<f64>::new(Vec3::from_array([0.0, 0.0, 2.0]));
+        assert_eq!(plane.get_normal().get_data(), [0.0, 0.0, 1.0]);
+        assert!(!plane.get_bounds().is_finite());
+    }
+
+    #[test]
+    fn hit() {
+        let plane = Plane::<f64>::new(Vec3::from_array([0.0, 0.0, 1.0]));
+
+        let origin = [3.0, -2.0, 8.0];
+        let direction = [0.0, 0.0, -2.0];
+        let ray = Ray::from_array(origin, direction);
+        match plane.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert_eq!(hit.point.get_data(), [3.0, -2.0, 0.0]);
+                assert_eq!(hit.normal.get_data(), [0.0, 0.0, 1.0]);
+                assert_eq!(hit.t, 4.0);
+                assert_eq!(hit.u, 3.0);
+                assert_eq!(hit.v, -2.0);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Far away from the origin
+        let origin = [1e6, 1e6, -1.0];
+        let direction = [0.0, 0.0, 1.0];
+        let ray = Ray::from_array(origin, direction);
+        assert!(plane.hit(&ray, 0.0, 100.0).is_some());
+
+        // Parallel to the plane
+        let origin = [0.0, 0.0, 1.0];
+        let direction = [1.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        assert!(plane.hit(&ray, 0.0, 100.0).is_none());
+
+        // Tilted plane
+        let plane = Plane::<f64>::new(Vec3::from_array([1.0, 1.0, 0.0]));
+        let origin = [2.0, 0.0, 0.0];
+        let direction = [-1.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        match plane.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert_eq!(hit.point.get_data(), [0.0, 0.0, 0.0]);
+                assert_eq!(hit.t, 2.0);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::boundingbox::BoundingBox;
+
+// A parallelogram spanned by two edges starting from a corner.
+// The normal is the cross product of the edges, it doesn't need to be aligned with an axis.
+pub struct Quad<T>
+    where T: Float
+{
+    corner: Vec3<T>,
+    edge_u: Vec3<T>,
+    edge_v: Vec3<T>,
+    normal: Vec3<T>,
+    w: Vec3<T>, // Projects the cross products onto the normal to get the planar coordinates
+    area: T,
+    bounds: BoundingBox<T>
+}
+
+impl<T> Quad<T>
+    where T: Float
+{
+    pub fn new(corner: Vec3<T>, edge_u: Vec3<T>, edge_v: Vec3<T>) -> Self {
+        let n = edge_u.cross(&edge_v);
+        let area = n.norm();
+        assert!(area > T::zero(), "Quad cannot have parallel edges");
+        let w = &n / n.dot(&n);
+        let normal = &n / area;
+        let bounds = Quad::compute_bounds(&corner, &edge_u, &edge_v);
+        Quad {
+            corner,
+            edge_u,
+            edge_v,
+            normal,
+            w,
+            area,
+            bounds
+        }
+    }
+
+    pub fn get_corner(&self) -> &Vec3<T> {
+        &self.corner
+    }
+
+    pub fn get_edge_u(&self) -> &Vec3<T> {
+        &self.edge_u
+    }
+
+    pub fn get_edge_v(&self) -> &Vec3<T> {
+        &self.edge_v
+    }
+
+    pub fn get_normal(&self) -> &Vec3<T> {
+        &self.normal
+    }
+
+    pub fn get_area(&self) -> T {
+        self.area
+    }
+
+    pub fn get_point(&self, u: T, v: T) -> Vec3<T> {
+        // The point at the given surface coordinates, uniform samples
+        // in [0, 1] give uniformly distributed points when used as an area light
+        &self.corner + &self.edge_u * u + &self.edge_v * v
+    }
+
+    fn compute_bounds(corner: &Vec3<T>, edge_u: &Vec3<T>, edge_v: &Vec3<T>) -> BoundingBox<T> {
+        let corners = [
+            corner + edge_u,
+            corner + edge_v,
+            corner + edge_u + edge_v
+        ];
+        let mut p0 = corner * T::one();
+        let mut p1 = corner * T::one();
+        for c in corners.iter() {
+            for i in 0..3 {
+                let x = c.get_data()[i];
+                if x < p0.get_data()[i] {
+                    p0.get_data_mut()[i] = x;
+                }
+                if x > p1.get_data()[i] {
+                    p1.get_data_mut()[i] = x;
+                }
+            }
+        }
+        BoundingBox::new(p0, p1)
+    }
+}
+
+impl<T> Hitable<T> for Quad<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let denominator = ray.get_direction().dot(&self.normal);
+        if denominator == T::zero() {
+            return None;
+        }
+
+        let t = (&self.corner - ray.get_origin()).dot(&self.normal) / denominator;
+        if t <= t_min || t > t_max {
+            return None;
+        }
+
+        // The coordinates of the point along the edges
+        let point = ray.get_point(t);
+        let p = &point - &self.corner;
+        let u = self.w.dot(&p.cross(&self.edge_v));
+        let v = self.w.dot(&self.edge_u.cross(&p));
+        if u < T::zero() || u > T::one() || v < T::zero() || v > T::one() {
+            return None;
+        }
+
+        let normal = &self.normal * T::one();
+        let mut tangent = &self.edge_u * T::one();
+        tangent.normalize();
+        let front_face = normal.dot(ray.get_direction()) < T::zero();
+        let hit = Hit {
+            point,
+            normal,
+            tangent,
+            front_face,
+            t,
+            u,
+            v
+        };
+
+        Some(hit)
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn init() {
+        let corner = Vec3::from_array([1.0, 0.0, 0.0]);
+        let edge_u = Vec3::from_array([2.0, 0.0, 0.0]);
+        let edge_v = Vec3::from_array([0.0, 4.0, 0.0]);
+        let quad = Quad::<f64>::new(corner, edge_u, edge_v);
+        assert_eq!(quad.get_normal().get_data(), [0.0, 0.0, 1.0]);
+        assert_eq!(quad.get_area(), 8.0);
+        assert_eq!(quad.get_point(0.5, 0.5).get_data(), [2.0, 2.0, 0.0]);
+
+        // Swapping the edges flips the normal
+        let corner = Vec3::from_array([1.0, 0.0, 0.0]);
+        let edge_u = Vec3::from_array([0.0, 4.0, 0.0]);
+        let edge_v = Vec3::from_array([2.0, 0.0, 0.0]);
+        let quad = Quad::<f64>::new(corner, edge_u, edge_v);
+        assert_eq!(quad.get_normal().get_data(), [0.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn hit() {
+        // A tilted panel
+        let corner = Vec3::from_array([0.0, 0.0, 0.0]);
+        let edge_u = Vec3::from_array([2.0, 0.0, 0.0]);
+        let edge_v = Vec3::from_array([0.0, 2.0, 2.0]);
+        let quad = Quad::<f64>::new(corner, edge_u, edge_v);
+
+        let origin = [0.5, 1.0, 8.0];
+        let direction = [0.0, 0.0, -2.0];
+        let ray = Ray::from_array(origin, direction);
+        match quad.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                let c = 0.5f64.sqrt();
+                let expected = [0.5, 1.0, 1.0];
+                for i in 0..3 {
+                    assert!((hit.point.get_data()[i] - expected[i]).abs() < 1e-12);
+                }
+                assert!((hit.normal.get_data()[1] + c).abs() < 1e-12);
+                assert!((hit.normal.get_data()[2] - c).abs() < 1e-12);
+                assert!((hit.t - 3.5).abs() < 1e-12);
+                assert!((hit.u - 0.25).abs() < 1e-12);
+                assert!((hit.v - 0.5).abs() < 1e-12);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Outside the edges
+        let origin = [2.5, 1.0, 8.0];
+        let ray = Ray::from_array(origin, direction);
+        assert!(quad.hit(&ray, 0.0, 100.0).is_none());
+
+        // A skewed parallelogram
+        let edge_u = Vec3::from_array([2.0, 0.0, 0.0]);
+        let edge_v = Vec3::from_array([1.0, 1.0, 0.0]);
+        let quad = Quad::<f64>::new(Vec3::new(), edge_u, edge_v);
+        let origin = [2.5, 0.75, 8.0];
+        let ray = Ray::from_array(origin, direction);
+        assert!(quad.hit(&ray, 0.0, 100.0).is_some());
+        let origin = [0.25, 0.75, 8.0];
+        let ray = Ray::from_array(origin, direction);
+        assert!(quad.hit(&ray, 0.0, 100.0).is_none());
+    }
+
+    #[test]
+    fn bounds() {
+        let corner = Vec3::from_array([1.0, 0.0, 0.0]);
+        let edge_u = Vec3::from_array([2.0, 0.0, 0.0]);
+        let edge_v = Vec3::from_array([0.0, 2.0, -2.0]);
+        let quad = Quad::<f64>::new(corner, edge_u, edge_v);
+        let bounds = quad.get_bounds();
+        assert_eq!(bounds.get_p0().get_data(), [1.0, 0.0, -2.0]);
+        assert_eq!(bounds.get_p1().get_data(), [3.0, 2.0, 0.0]);
+    }
+}
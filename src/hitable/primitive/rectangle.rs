@@ -104,10 +104,18 @@ impl<T> Hitable<T> for Rectangle<T>
 
         let point = ray.get_point(t);
         let normal = &self.normal * T::one();
+        let tangent = Rectangle::<T>::length_to_point(T::one(), &self.width_axis);
+        let u = (width - w0) / (w1 - w0);
+        let v = (height - h0) / (h1 - h0);
+        let front_face = normal.dot(ray.get_direction()) < T::zero();
         let hit = Hit {
             point,
             normal,
-            t
+            tangent,
+            front_face,
+            t,
+            u,
+            v
         };
 
         Some(hit)
@@ -193,6 +201,8 @@ mod tests {
                 assert_eq!(hit.point.get_data(), [0.5, 1.5, 0.0]);
                 assert_eq!(hit.normal.get_data(), [0.0, 0.0, 1.0]);
                 assert_eq!(hit.t, 4.0);
+                assert_eq!(hit.u, 0.75);
+                assert_eq!(hit.v, 0.875);
             },
             None => {
                 assert!(false);
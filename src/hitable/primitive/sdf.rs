@@ -0,0 +1,433 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::boundingbox::BoundingBox;
+use crate::utils::tangent_basis;
+
+// A signed distance field: negative inside the surface, positive outside.
+// The distance may be underestimated, but never overestimated,
+// otherwise sphere tracing can step through the surface.
+pub trait Sdf<T>
+    where T: Float
+{
+    fn distance(&self, point: &Vec3<T>) -> T;
+}
+
+impl<T, F> Sdf<T> for F
+    where T: Float, F: Fn(&Vec3<T>) -> T
+{
+    fn distance(&self, point: &Vec3<T>) -> T {
+        self(point)
+    }
+}
+
+// Intersects a distance field by sphere tracing inside conservative bounds
+pub struct SdfHitable<T>
+    where T: Float
+{
+    sdf: Box<dyn Sdf<T>>,
+    bounds: BoundingBox<T>,
+    epsilon: T,
+    max_steps: usize,
+    step_scale: T // Below 1 for fields that overestimate the distance, such as twisted ones
+}
+
+impl<T> SdfHitable<T>
+    where T: Float
+{
+    pub fn new(sdf: Box<dyn Sdf<T>>, bounds: BoundingBox<T>) -> Self {
+        SdfHitable {
+            sdf,
+            bounds,
+            epsilon: T::from(0.0001).unwrap(),
+            max_steps: 256,
+            step_scale: T::one()
+        }
+    }
+
+    pub fn get_epsilon(&self) -> T {
+        self.epsilon
+    }
+
+    pub fn set_epsilon(&mut self, epsilon: T) {
+        self.epsilon = epsilon;
+    }
+
+    pub fn get_max_steps(&self) -> usize {
+        self.max_steps
+    }
+
+    pub fn set_max_steps(&mut self, max_steps: usize) {
+        self.max_steps = max_steps;
+    }
+
+    pub fn get_step_scale(&self) -> T {
+        self.step_scale
+    }
+
+    pub fn set_step_scale(&mut self, step_scale: T) {
+        self.step_scale = step_scale;
+    }
+
+    pub fn get_normal(&self, point: &Vec3<T>) -> Vec3<T> {
+        // Central differences of the field
+        let mut normal = Vec3::<T>::new();
+        for i in 0..3 {
+            let mut offset = Vec3::<T>::new();
+            offset.get_data_mut()[i] = self.epsilon;
+            let d1 = self.sdf.distance(&(point + &offset));
+            let d0 = self.sdf.distance(&(point - &offset));
+            normal.get_data_mut()[i] = d1 - d0;
+        }
+        normal.normalize();
+        normal
+    }
+}
+
+impl<T> Hitable<T> for SdfHitable<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let (t_enter, t_exit) = self.bounds.intersect(ray, t_min, t_max)?;
+        let speed = ray.get_direction().norm();
+        let mut t = t_enter;
+        let mut steps = 0;
+
+        // Rays leaving the surface (i.e. after a bounce) start on it,
+        // move past the threshold before looking for the next crossing
+        if t_enter <= t_min {
+            let mut distance = self.sdf.distance(&ray.get_point(t));
+            while distance.abs() < self.epsilon && steps < self.max_steps {
+                t = t + self.epsilon / speed;
+                distance = self.sdf.distance(&ray.get_point(t));
+                steps += 1;
+            }
+        }
+
+        // Rays can start inside the surface too (i.e. after a refraction),
+        // in which case the absolute distance is traced
+        while steps < self.max_steps && t <= t_exit {
+            let point = ray.get_point(t);
+            let distance = self.sdf.distance(&point);
+            if distance.abs() < self.epsilon {
+                let normal = self.get_normal(&point);
+                // There is no natural parametrization of the surface
+                let (tangent, _) = tangent_basis(&normal);
+                let front_face = normal.dot(ray.get_direction()) < T::zero();
+                let hit = Hit {
+                    point,
+                    normal,
+                    tangent,
+                    front_face,
+                    t,
+                    u: T::zero(),
+                    v: T::zero()
+                };
+                return Some(hit);
+            }
+            t = t + self.step_scale * distance.abs() / speed;
+            steps += 1;
+        }
+
+        None
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+}
+
+// Built-in distance fields
+
+pub struct SphereSdf<T>
+    where T: Float
+{
+    radius: T
+}
+
+impl<T> SphereSdf<T>
+    where T: Float
+{
+    pub fn new(radius: T) -> Self {
+        SphereSdf { radius }
+    }
+}
+
+impl<T> Sdf<T> for SphereSdf<T>
+    where T: Float
+{
+    fn distance(&self, point: &Vec3<T>) -> T {
+        point.norm() - self.radius
+    }
+}
+
+// A box centered at the origin, with its edges rounded off by radius
+pub struct RoundedBoxSdf<T>
+    where T: Float
+{
+    half_size: Vec3<T>,
+    radius: T
+}
+
+impl<T> RoundedBoxSdf<T>
+    where T: Float
+{
+    pub fn new(size: Vec3<T>, radius: T) -> Self {
+        let half_size = &size * T::from(0.5).unwrap();
+        RoundedBoxSdf {
+            half_size,
+            radius
+        }
+    }
+}
+
+impl<T> Sdf<T> for RoundedBoxSdf<T>
+    where T: Float
+{
+    fn distance(&self, point: &Vec3<T>) -> T {
+        let mut outside = Vec3::<T>::new();
+        let mut inside = T::neg_infinity();
+        for i in 0..3 {
+            let q = point.get_data()[i].abs() - self.half_size.get_data()[i] + self.radius;
+            outside.get_data_mut()[i] = q.max(T::zero());
+            inside = inside.max(q);
+        }
+        outside.norm() + inside.min(T::zero()) - self.radius
+    }
+}
+
+// Combinators
+
+pub struct Translate<T>
+    where T: Float
+{
+    sdf: Box<dyn Sdf<T>>,
+    translation: Vec3<T>
+}
+
+impl<T> Translate<T>
+    where T: Float
+{
+    pub fn new(sdf: Box<dyn Sdf<T>>, translation: Vec3<T>) -> Self {
+        Translate {
+            sdf,
+            translation
+        }
+    }
+}
+
+impl<T> Sdf<T> for Translate<T>
+    where T: Float
+{
+    fn distance(&self, point: &Vec3<T>) -> T {
+        self.sdf.distance(&(point - &self.translation))
+    }
+}
+
+// Blends two fields together, smoothness is the size of the blended region (0 for a plain union)
+pub struct SmoothUnion<T>
+    where T: Float
+{
+    left: Box<dyn Sdf<T>>,
+    right: Box<dyn Sdf<T>>,
+    smoothness: T
+}
+
+impl<T> SmoothUnion<T>
+    where T: Float
+{
+    pub fn new(left: Box<dyn Sdf<T>>, right: Box<dyn Sdf<T>>, smoothness: T) -> Self {
+        SmoothUnion {
+            left,
+            right,
+            smoothness
+        }
+    }
+}
+
+impl<T> Sdf<T> for SmoothUnion<T>
+    where T: Float
+{
+    fn distance(&self, point: &Vec3<T>) -> T {
+        // Polynomial smooth minimum
+        let a = self.left.distance(point);
+        let b = self.right.distance(point);
+        if self.smoothness <= T::zero() {
+            // Nothing to blend, a plain union
+            return a.min(b);
+        }
+        let half = T::from(0.5).unwrap();
+        let h = half + half * (b - a) / self.smoothness;
+        let h = h.max(T::zero()).min(T::one());
+        b + (a - b) * h - self.smoothness * h * (T::one() - h)
+    }
+}
+
+// Repeats a field infinitely, along the axes with a non zero period
+pub struct Repetition<T>
+    where T: Float
+{
+    sdf: Box<dyn Sdf<T>>,
+    period: Vec3<T>
+}
+
+impl<T> Repetition<T>
+    where T: Float
+{
+    pub fn new(sdf: Box<dyn Sdf<T>>, period: Vec3<T>) -> Self {
+        Repetition {
+            sdf,
+            period
+        }
+    }
+}
+
+impl<T> Sdf<T> for Repetition<T>
+    where T: Float
+{
+    fn distance(&self, point: &Vec3<T>) -> T {
+        let mut q = point * T::one();
+        for i in 0..3 {
+            let period = self.period.get_data()[i];
+            if period != T::zero() {
+                let x = q.get_data()[i];
+                q.get_data_mut()[i] = x - period * (x / period).round();
+            }
+        }
+        self.sdf.distance(&q)
+    }
+}
+
+// Rotates the XY plane around the Z axis by rate radians per unit of height.
+// The result overestimates the distance, the hitable step scale should be lowered.
+pub struct Twist<T>
+    where T: Float
+{
+    sdf: Box<dyn Sdf<T>>,
+    rate: T
+}
+
+impl<T> Twist<T>
+    where T: Float
+{
+    pub fn new(sdf: Box<dyn Sdf<T>>, rate: T) -> Self {
+        Twist {
+            sdf,
+            rate
+        }
+    }
+}
+
+impl<T> Sdf<T> for Twist<T>
+    where T: Float
+{
+    fn distance(&self, point: &Vec3<T>) -> T {
+        let x = point.get_data()[0];
+        let y = point.get_data()[1];
+        let z = point.get_data()[2];
+        let angle = self.rate * z;
+        let (sin, cos) = (angle.sin(), angle.cos());
+        let q = Vec3::from_array([cos * x - sin * y, sin * x + cos * y, z]);
+        self.sdf.distance(&q)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sphere_bounds(radius: f64) -> BoundingBox<f64> {
+        BoundingBox::new(
+            Vec3::from_array([- radius; 3]),
+            Vec3::from_array([radius; 3])
+        )
+    }
+
+    #[test]
+    fn distance() {
+        let point = Vec3::from_array([3.0, 0.0, 0.0]);
+
+        let sphere = SphereSdf::<f64>::new(2.0);
+        assert_eq!(sphere.distance(&point), 1.0);
+
+        let cube = RoundedBoxSdf::<f64>::new(Vec3::from_array([2.0, 2.0, 2.0]), 0.0);
+        assert_eq!(cube.distance(&point), 2.0);
+        assert_eq!(cube.distance(&Vec3::new()), -1.0);
+
+        // Rounding the corners moves them inwards
+        let corner = Vec3::from_array([2.0, 2.0, 2.0]);
+        let rounded = RoundedBoxSdf::<f64>::new(Vec3::from_array([2.0, 2.0, 2.0]), 0.5);
+        assert!(rounded.distance(&corner) > cube.distance(&corner));
+
+        let sphere = Translate::new(Box::new(SphereSdf::<f64>::new(1.0)), Vec3::from_array([3.0, 0.0, 0.0]));
+        assert_eq!(sphere.distance(&point), -1.0);
+
+        let repeated = Repetition::new(Box::new(SphereSdf::<f64>::new(1.0)), Vec3::from_array([4.0, 0.0, 0.0]));
+        assert_eq!(repeated.distance(&Vec3::from_array([8.5, 0.0, 0.0])), -0.5);
+        assert_eq!(repeated.distance(&Vec3::from_array([8.0, 2.0, 0.0])), 1.0);
+
+        // The blend is below both fields between them
+        let left = Translate::new(Box::new(SphereSdf::<f64>::new(1.0)), Vec3::from_array([-1.0, 0.0, 0.0]));
+        let right = Translate::new(Box::new(SphereSdf::<f64>::new(1.0)), Vec3::from_array([1.0, 0.0, 0.0]));
+        let blend = SmoothUnion::new(Box::new(left), Box::new(right), 1.0);
+        let point = Vec3::from_array([0.0, 1.0, 0.0]);
+        assert!(blend.distance(&point) < 2.0f64.sqrt() - 1.0);
+
+        // Without smoothness it is a plain union
+        let left = Translate::new(Box::new(SphereSdf::<f64>::new(1.0)), Vec3::from_array([-1.0, 0.0, 0.0]));
+        let right = Translate::new(Box::new(SphereSdf::<f64>::new(1.0)), Vec3::from_array([1.0, 0.0, 0.0]));
+        let union = SmoothUnion::new(Box::new(left), Box::new(right), 0.0);
+        assert_eq!(union.distance(&point), 2.0f64.sqrt() - 1.0);
+
+        // A quarter turn at z = 1
+        let cube = RoundedBoxSdf::<f64>::new(Vec3::from_array([4.0, 2.0, 8.0]), 0.0);
+        let twisted = Twist::new(Box::new(cube), 0.5 * std::f64::consts::PI);
+        assert!(twisted.distance(&Vec3::from_array([0.0, 1.5, 1.0])) < 0.0);
+        assert!(twisted.distance(&Vec3::from_array([1.5, 0.0, 1.0])) > 0.0);
+    }
+
+    #[test]
+    fn hit() {
+        let sdf = SdfHitable::<f64>::new(Box::new(SphereSdf::new(2.0)), sphere_bounds(2.0));
+
+        let origin = [-8.0, 0.0, 0.0];
+        let direction = [2.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        match sdf.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert!((hit.point.get_data()[0] + 2.0).abs() < 1e-3);
+                assert!((hit.normal.get_data()[0] + 1.0).abs() < 1e-6);
+                assert!((hit.t - 3.0).abs() < 1e-3);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        let origin = [-8.0, 2.5, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        assert!(sdf.hit(&ray, 0.0, 100.0).is_none());
+
+        // A closure, leaving from the surface towards the inside
+        let closure = |p: &Vec3<f64>| p.norm() - 1.0;
+        let sdf = SdfHitable::<f64>::new(Box::new(closure), sphere_bounds(1.0));
+        let origin = [-1.0, 0.0, 0.0];
+        let direction = [1.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        match sdf.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert!((hit.point.get_data()[0] - 1.0).abs() < 1e-3);
+                assert!((hit.normal.get_data()[0] - 1.0).abs() < 1e-6);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+    }
+}
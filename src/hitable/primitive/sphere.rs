@@ -4,6 +4,7 @@ use crate::ray::Ray;
 use crate::hit::Hit;
 use crate::hitable::Hitable;
 use crate::boundingbox::BoundingBox;
+use crate::utils::azimuthal_tangent;
 
 pub struct Sphere<T>
     where T: Float
@@ -39,6 +40,31 @@ impl<T> Sphere<T>
         let p1 = &one * self.get_radius();
         self.bounds = BoundingBox::<T>::new(p0, p1);
     }
+
+    fn get_hit(&self, ray: &Ray<T>, t: T) -> Hit<T> {
+        let point = ray.get_point(t);
+        let normal = (&point) / self.get_radius();
+
+        // Spherical coordinates around the Z axis
+        let pi = T::from(std::f64::consts::PI).unwrap();
+        let phi = normal.get_data()[1].atan2(normal.get_data()[0]);
+        let cos_theta = normal.get_data()[2];
+        let cos_theta = if cos_theta > T::one() { T::one() } else if cos_theta < - T::one() { - T::one() } else { cos_theta };
+        let u = (phi + pi) / (pi + pi);
+        let v = cos_theta.acos() / pi;
+        let tangent = azimuthal_tangent(&point);
+
+        let front_face = normal.dot(ray.get_direction()) < T::zero();
+        Hit {
+            point,
+            normal,
+            tangent,
+            front_face,
+            t,
+            u,
+            v
+        }
+    }
 }
 
 impl<T> Hitable<T> for Sphere<T>
@@ -76,15 +102,26 @@ impl<T> Hitable<T> for Sphere<T>
                 else if t1 >= t_min && t1 < t_max { t1 }
                 else { return None; };
 
-        let point = ray.get_point(t);
-        let normal = (&point) / self.get_radius();
-        let hit = Hit {
-            point,
-            normal,
-            t
-        };
+        Some(self.get_hit(ray, t))
+    }
 
-        Some(hit)
+    fn hit_all(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Vec<Hit<T>> {
+        // Both roots of the equation solved in hit
+        let oc = ray.get_origin();
+        let a = ray.get_direction().dot(ray.get_direction());
+        let b = ray.get_direction().dot(oc);
+        let c = oc.dot(oc) - self.get_radius() * self.get_radius();
+        let discriminant = b * b - a * c;
+        if discriminant <= T::zero() {
+            return vec![];
+        }
+        let discriminant = discriminant.sqrt();
+        let t0 = (- b - discriminant) / a;
+        let t1 = (- b + discriminant) / a;
+        [t0, t1].iter()
+            .filter(|t| **t >= t_min && **t < t_max)
+            .map(|t| self.get_hit(ray, *t))
+            .collect()
     }
 
     fn get_bounds(&self) -> &BoundingBox<T> {
@@ -131,7 +168,26 @@ mod tests {
             Some(hit) => {
                 assert_eq!(hit.point.get_data(), [-2.0, 0.0, 0.0]);
                 assert_eq!(hit.normal.get_data(), [-1.0, 0.0, 0.0]);
+                assert_eq!(hit.tangent.get_data(), [0.0, -1.0, 0.0]);
+                assert!(hit.front_face);
                 assert_eq!(hit.t, 3.0);
+                assert_eq!(hit.u, 1.0);
+                assert_eq!(hit.v, 0.5);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // From the inside the back face is hit, the shading normal still faces the ray
+        let origin = [0.0, 0.0, 0.0];
+        let direction = [1.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        match sphere.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert_eq!(hit.normal.get_data(), [1.0, 0.0, 0.0]);
+                assert!(!hit.front_face);
+                assert_eq!(hit.get_shading_normal().get_data(), [-1.0, 0.0, 0.0]);
             },
             None => {
                 assert!(false);
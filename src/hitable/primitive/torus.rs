@@ -0,0 +1,214 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::boundingbox::BoundingBox;
+use crate::utils::{solve_quartic, azimuthal_tangent};
+
+// A torus around the Z axis, centered at the origin.
+// The tube of radius minor_radius follows a circle of radius major_radius in the XY plane.
+pub struct Torus<T>
+    where T: Float
+{
+    major_radius: T,
+    minor_radius: T,
+    bounds: BoundingBox<T>
+}
+
+impl<T> Torus<T>
+    where T: Float
+{
+    pub fn new(major_radius: T, minor_radius: T) -> Self {
+        let extent = major_radius + minor_radius;
+        let bounds = BoundingBox::new(
+            Vec3::from_array([- extent, - extent, - minor_radius]),
+            Vec3::from_array([extent, extent, minor_radius])
+        );
+        Torus {
+            major_radius,
+            minor_radius,
+            bounds
+        }
+    }
+
+    pub fn get_major_radius(&self) -> T {
+        self.major_radius
+    }
+
+    pub fn get_minor_radius(&self) -> T {
+        self.minor_radius
+    }
+}
+
+impl<T> Hitable<T> for Torus<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        // Intersection of a line and a torus:
+        //
+        // (dot(p, p) + R * R - r * r)^2 = 4 * R * R * (x * x + y * y)
+        //
+        // Substituting p(t) gives a quartic in t. Its coefficients lose precision
+        // quickly when the origin is far away, so the origin is first moved
+        // to where the ray enters the bounding sphere of the torus.
+        let direction = ray.get_direction();
+        let dd = direction.dot(direction);
+        let extent = self.major_radius + self.minor_radius;
+        let oc = ray.get_origin();
+        let b = direction.dot(oc);
+        let c = oc.dot(oc) - extent * extent;
+        let discriminant = b * b - dd * c;
+        if discriminant <= T::zero() {
+            return None;
+        }
+        let t_enter = (- b - discriminant.sqrt()) / dd;
+        let t_shift = if t_enter > T::zero() { t_enter } else { T::zero() };
+        let origin = ray.get_point(t_shift);
+
+        let o = origin.get_data();
+        let d = direction.get_data();
+        let two = T::from(2.0).unwrap();
+        let four = T::from(4.0).unwrap();
+        let r2 = self.major_radius * self.major_radius;
+        let od = origin.dot(direction);
+        let k = origin.dot(&origin) + r2 - self.minor_radius * self.minor_radius;
+        let dxy = d[0] * d[0] + d[1] * d[1];
+        let oxy_d = o[0] * d[0] + o[1] * d[1];
+        let oxy = o[0] * o[0] + o[1] * o[1];
+
+        let roots = solve_quartic(
+            dd * dd,
+            four * dd * od,
+            four * od * od + two * dd * k - four * r2 * dxy,
+            four * od * k - two * four * r2 * oxy_d,
+            k * k - four * r2 * oxy
+        );
+
+        let t = roots.iter()
+            .map(|t| *t + t_shift)
+            .find(|t| *t >= t_min && *t < t_max)?;
+
+        // The normal points from the center of the tube to the point
+        let point = ray.get_point(t);
+        let x = point.get_data()[0];
+        let y = point.get_data()[1];
+        let z = point.get_data()[2];
+        let rxy = (x * x + y * y).sqrt();
+        let center = if rxy > T::zero() {
+            Vec3::from_array([x / rxy * self.major_radius, y / rxy * self.major_radius, T::zero()])
+        } else {
+            Vec3::<T>::new()
+        };
+        let mut normal = &point - &center;
+        normal.normalize();
+
+        // u goes around the Z axis, v around the tube starting from the outside
+        let pi = T::from(std::f64::consts::PI).unwrap();
+        let phi = y.atan2(x);
+        let theta = z.atan2(rxy - self.major_radius);
+        let u = (phi + pi) / (pi + pi);
+        let v = (theta + pi) / (pi + pi);
+        let tangent = azimuthal_tangent(&point);
+
+        let front_face = normal.dot(ray.get_direction()) < T::zero();
+        Some(Hit {
+            point,
+            normal,
+            tangent,
+            front_face,
+            t,
+            u,
+            v
+        })
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit() {
+        let torus = Torus::<f64>::new(3.0, 1.0);
+
+        let origin = [-8.0, 0.0, 0.0];
+        let direction = [2.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        match torus.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert!((hit.point.get_data()[0] + 4.0).abs() < 1e-9);
+                assert!((hit.normal.get_data()[0] + 1.0).abs() < 1e-9);
+                assert!((hit.t - 2.0).abs() < 1e-9);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Through the hole
+        let origin = [0.0, 0.0, 8.0];
+        let direction = [0.0, 0.0, -1.0];
+        let ray = Ray::from_array(origin, direction);
+        assert!(torus.hit(&ray, 0.0, 100.0).is_none());
+
+        // From the top of the tube
+        let origin = [0.0, 3.0, 8.0];
+        let ray = Ray::from_array(origin, direction);
+        match torus.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert!((hit.point.get_data()[2] - 1.0).abs() < 1e-9);
+                assert!((hit.normal.get_data()[2] - 1.0).abs() < 1e-9);
+                assert!((hit.t - 7.0).abs() < 1e-9);
+                assert!((hit.u - 0.75).abs() < 1e-9);
+                assert!((hit.v - 0.75).abs() < 1e-9);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // From inside the tube, the ray only leaves it
+        let origin = [3.0, 0.0, 0.0];
+        let direction = [0.0, 0.0, 1.0];
+        let ray = Ray::from_array(origin, direction);
+        match torus.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert!((hit.t - 1.0).abs() < 1e-9);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Far away from the torus
+        let origin = [-1e6, 0.0, 0.5];
+        let direction = [1.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        match torus.hit(&ray, 0.0, 1e7) {
+            Some(hit) => {
+                let x = - 3.0 - 0.75f64.sqrt();
+                assert!((hit.point.get_data()[0] - x).abs() < 1e-6);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+    }
+
+    #[test]
+    fn bounds() {
+        let torus = Torus::<f64>::new(3.0, 1.0);
+        let bounds = torus.get_bounds();
+        assert_eq!(bounds.get_p0().get_data(), [-4.0, -4.0, -1.0]);
+        assert_eq!(bounds.get_p1().get_data(), [4.0, 4.0, 1.0]);
+    }
+}
@@ -0,0 +1,295 @@
+use std::rc::Rc;
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::boundingbox::BoundingBox;
+
+// Places a shared hitable in the scene without copying it.
+// The geometry is scaled, then rotated around an axis through the origin, then translated.
+pub struct Instance<T>
+    where T: Float
+{
+    geometry: Rc<dyn Hitable<T>>,
+    translation: Vec3<T>,
+    rotation: [Vec3<T>; 3], // Rows of the rotation matrix
+    scale: T,
+    bounds: BoundingBox<T>
+}
+
+impl<T> Instance<T>
+    where T: Float
+{
+    pub fn new(geometry: Rc<dyn Hitable<T>>) -> Self {
+        let mut instance = Instance {
+            geometry,
+            translation: Vec3::<T>::new(),
+            rotation: [
+                Vec3::from_array([T::one(), T::zero(), T::zero()]),
+                Vec3::from_array([T::zero(), T::one(), T::zero()]),
+                Vec3::from_array([T::zero(), T::zero(), T::one()])
+            ],
+            scale: T::one(),
+            bounds: BoundingBox::<T>::new(Vec3::<T>::new(), Vec3::<T>::new())
+        };
+        instance.update_bounds();
+        instance
+    }
+
+    pub fn get_geometry(&self) -> &Rc<dyn Hitable<T>> {
+        &self.geometry
+    }
+
+    pub fn get_translation(&self) -> &Vec3<T> {
+        &self.translation
+    }
+
+    pub fn set_translation(&mut self, translation: Vec3<T>) {
+        self.translation = translation;
+        self.update_bounds();
+    }
+
+    pub fn get_scale(&self) -> T {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: T) {
+        assert!(scale > T::zero(), "Instance scale must be positive");
+        self.scale = scale;
+        self.update_bounds();
+    }
+
+    pub fn set_rotation(&mut self, axis: Vec3<T>, angle: T) {
+        // Rodrigues' rotation formula in matrix form
+        let mut axis = axis;
+        axis.normalize();
+        let k = axis.get_data();
+        let (sin, cos) = (angle.sin(), angle.cos());
+        let one_minus_cos = T::one() - cos;
+        let cross = [
+            [T::zero(), - k[2], k[1]],
+            [k[2], T::zero(), - k[0]],
+            [- k[1], k[0], T::zero()]
+        ];
+        for i in 0..3 {
+            for j in 0..3 {
+                let identity = if i == j { cos } else { T::zero() };
+                self.rotation[i].get_data_mut()[j] = identity + sin * cross[i][j] + one_minus_cos * k[i] * k[j];
+            }
+        }
+        self.update_bounds();
+    }
+
+    fn rotate(&self, vector: &Vec3<T>) -> Vec3<T> {
+        Vec3::from_array([
+            self.rotation[0].dot(vector),
+            self.rotation[1].dot(vector),
+            self.rotation[2].dot(vector)
+        ])
+    }
+
+    fn rotate_inverse(&self, vector: &Vec3<T>) -> Vec3<T> {
+        // The inverse of a rotation is its transpose
+        &self.rotation[0] * vector.get_data()[0]
+            + &self.rotation[1] * vector.get_data()[1]
+            + &self.rotation[2] * vector.get_data()[2]
+    }
+
+    fn to_world(&self, mut hit: Hit<T>) -> Hit<T> {
+        hit.point = self.rotate(&(&hit.point * self.scale)) + &self.translation;
+        hit.normal = self.rotate(&hit.normal);
+        hit.tangent = self.rotate(&hit.tangent);
+        hit
+    }
+
+    fn to_local(&self, ray: &Ray<T>) -> Ray<T> {
+        // The ray parameter t is the same in both spaces
+        let origin = self.rotate_inverse(&(ray.get_origin() - &self.translation)) / self.scale;
+        let direction = self.rotate_inverse(ray.get_direction()) / self.scale;
+        Ray::from_vec(origin, direction)
+    }
+
+    fn update_bounds(&mut self) {
+        let bounds = self.geometry.get_bounds();
+        if !bounds.is_finite() {
+            self.bounds = BoundingBox::infinite();
+            return;
+        }
+
+        // The box around the eight transformed corners
+        let mut p0 = Vec3::from_array([T::infinity(); 3]);
+        let mut p1 = Vec3::from_array([T::neg_infinity(); 3]);
+        for i in 0..8 {
+            let mut corner = Vec3::<T>::new();
+            for j in 0..3 {
+                let (min, max) = bounds.get_axis_bounds(j);
+                corner.get_data_mut()[j] = if i & (1 << j) == 0 { min } else { max };
+            }
+            let corner = self.rotate(&(&corner * self.scale)) + &self.translation;
+            for j in 0..3 {
+                let x = corner.get_data()[j];
+                if x < p0.get_data()[j] {
+                    p0.get_data_mut()[j] = x;
+                }
+                if x > p1.get_data()[j] {
+                    p1.get_data_mut()[j] = x;
+                }
+            }
+        }
+        self.bounds = BoundingBox::new(p0, p1);
+    }
+}
+
+impl<T> Hitable<T> for Instance<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let local_ray = self.to_local(ray);
+        let hit = self.geometry.hit(&local_ray, t_min, t_max)?;
+        Some(self.to_world(hit))
+    }
+
+    fn hit_all(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Vec<Hit<T>> {
+        let local_ray = self.to_local(ray);
+        self.geometry.hit_all(&local_ray, t_min, t_max)
+            .into_iter()
+            .map(|hit| self.to_world(hit))
+            .collect()
+    }
+
+    fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        let local_ray = self.to_local(ray);
+        self.geometry.any_hit(&local_ray, t_min, t_max)
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        // The shared geometry can't be moved out of the instance
+        self
+    }
+
+    fn is_primitive(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::primitive::{Sphere, Cube};
+    use crate::actor::SharedGeometry;
+    use crate::material::Material;
+    use crate::material::plain::PlainMaterial;
+    use crate::texture::uniform::UniformTexture;
+    use crate::tree::Tree;
+    use crate::tree::binary::BinaryTree;
+
+    #[test]
+    fn bounds() {
+        let geometry: Rc<dyn Hitable<f64>> = Rc::new(Cube::new(2.0, 4.0, 6.0));
+        let mut instance = Instance::new(Rc::clone(&geometry));
+        assert_eq!(instance.get_bounds().get_p0().get_data(), [-1.0, -2.0, -3.0]);
+        assert_eq!(instance.get_bounds().get_p1().get_data(), [1.0, 2.0, 3.0]);
+
+        instance.set_scale(2.0);
+        instance.set_translation(Vec3::from_array([10.0, 0.0, 0.0]));
+        assert_eq!(instance.get_bounds().get_p0().get_data(), [8.0, -4.0, -6.0]);
+        assert_eq!(instance.get_bounds().get_p1().get_data(), [12.0, 4.0, 6.0]);
+
+        // A quarter turn around Z swaps the X and Y extents
+        instance.set_rotation(Vec3::from_array([0.0, 0.0, 1.0]), 0.5 * std::f64::consts::PI);
+        let expected_p0 = [6.0, -2.0, -6.0];
+        let expected_p1 = [14.0, 2.0, 6.0];
+        for i in 0..3 {
+            assert!((instance.get_bounds().get_p0().get_data()[i] - expected_p0[i]).abs() < 1e-12);
+            assert!((instance.get_bounds().get_p1().get_data()[i] - expected_p1[i]).abs() < 1e-12);
+        }
+
+        // The geometry is shared, not copied
+        assert_eq!(Rc::strong_count(&geometry), 2);
+    }
+
+    #[test]
+    fn hit() {
+        let geometry: Rc<dyn Hitable<f64>> = Rc::new(Sphere::new(1.0));
+        let mut instance = Instance::new(geometry);
+        instance.set_scale(2.0);
+        instance.set_translation(Vec3::from_array([0.0, 0.0, 4.0]));
+
+        let origin = [0.0, 0.0, 10.0];
+        let direction = [0.0, 0.0, -1.0];
+        let ray = Ray::from_array(origin, direction);
+        match instance.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert_eq!(hit.point.get_data(), [0.0, 0.0, 6.0]);
+                assert_eq!(hit.normal.get_data(), [0.0, 0.0, 1.0]);
+                assert_eq!(hit.t, 4.0);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Rotating the normals with the geometry
+        let geometry: Rc<dyn Hitable<f64>> = Rc::new(Cube::new(2.0, 2.0, 2.0));
+        let mut instance = Instance::new(geometry);
+        instance.set_rotation(Vec3::from_array([0.0, 0.0, 1.0]), 0.25 * std::f64::consts::PI);
+        let origin = [10.0, 0.0, 0.0];
+        let direction = [-1.0, 0.0, 0.0];
+        let ray = Ray::from_array(origin, direction);
+        match instance.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                let c = 0.5f64.sqrt();
+                assert!((hit.t - (10.0 - 2.0f64.sqrt())).abs() < 1e-12);
+                assert!((hit.normal.get_data()[0] - c).abs() < 1e-12);
+                assert!((hit.normal.get_data()[1].abs() - c).abs() < 1e-12);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+    }
+
+    #[test]
+    fn shared() {
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.0, 0.0])));
+        let material = Box::new(PlainMaterial::new(texture));
+        let geometry = SharedGeometry::<f64>::new(Box::new(Sphere::new(1.0)), material);
+
+        // A row of spheres, each one its own actor in the tree
+        let mut tree = BinaryTree::new();
+        for i in 0..10 {
+            let mut instance = geometry.create_instance();
+            instance.set_translation(Vec3::from_array([4.0 * i as f64, 0.0, 0.0]));
+            let material = if i == 5 {
+                let texture = Box::new(UniformTexture::new(Vec3::from_array([0.0, 1.0, 0.0])));
+                Some(Box::new(PlainMaterial::new(texture)) as Box<dyn Material<f64>>)
+            } else {
+                None
+            };
+            let actor = geometry.create_actor(instance, material);
+            assert!(tree.add_actor(Rc::new(actor)));
+        }
+        assert_eq!(Rc::strong_count(geometry.get_hitable()), 11);
+        assert_eq!(Rc::strong_count(geometry.get_material()), 10);
+
+        let origin = [20.0, 0.0, 10.0];
+        let direction = [0.0, 0.0, -1.0];
+        let ray = Ray::from_array(origin, direction);
+        match tree.get_hit(&ray, 0.0, 100.0) {
+            Some((actor, hit)) => {
+                assert_eq!(hit.point.get_data(), [20.0, 0.0, 1.0]);
+                let scatter = actor.material.scatter(&ray, &hit);
+                assert_eq!(scatter.attenuation.get_data(), [0.0, 1.0, 0.0]);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+    }
+}
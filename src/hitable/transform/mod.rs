@@ -1,3 +1,7 @@
 pub mod translation;
+pub mod instance;
+pub mod onesided;
 
-pub use translation::Translation;
\ No newline at end of file
+pub use translation::Translation;
+pub use instance::Instance;
+pub use onesided::OneSided;
\ No newline at end of file
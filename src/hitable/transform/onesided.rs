@@ -0,0 +1,86 @@
+use crate::float::Float;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::boundingbox::BoundingBox;
+use crate::utils::step_past;
+
+// Culls the hits on the back face of the wrapped hitable, e.g. for light portals
+pub struct OneSided<T>
+    where T: Float
+{
+    wrapped: Box<dyn Hitable<T>>
+}
+
+impl<T> OneSided<T>
+    where T: Float
+{
+    pub fn new(wrapped: Box<dyn Hitable<T>>) -> Self {
+        OneSided {
+            wrapped
+        }
+    }
+}
+
+impl<T> Hitable<T> for OneSided<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let mut t_min = t_min;
+        loop {
+            let hit = self.wrapped.hit(ray, t_min, t_max)?;
+            if hit.front_face {
+                return Some(hit);
+            }
+            t_min = step_past(hit.t);
+        }
+    }
+
+    fn hit_all(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Vec<Hit<T>> {
+        self.wrapped.hit_all(ray, t_min, t_max).into_iter().filter(|hit| hit.front_face).collect()
+    }
+
+    fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        self.hit(ray, t_min, t_max).is_some()
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        self.wrapped.get_bounds()
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self.wrapped
+    }
+
+    fn is_primitive(&self) -> bool {
+        false
+    }
+
+    fn is_one_sided(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::primitive::Sphere;
+
+    #[test]
+    fn hit() {
+        let hitable = Box::new(OneSided::new(Box::new(Sphere::<f64>::new(1.0))));
+        assert!(hitable.is_one_sided());
+
+        // From outside only the entry is kept, from inside the sphere is invisible
+        let outside = Ray::from_array([0.0, 0.0, 5.0], [0.0, 0.0, -1.0]);
+        let inside = Ray::from_array([0.0, 0.0, 0.0], [0.0, 0.0, -1.0]);
+        assert_eq!(hitable.hit(&outside, 0.0, 100.0).unwrap().t, 4.0);
+        assert_eq!(hitable.hit_all(&outside, 0.0, 100.0).len(), 1);
+        assert!(hitable.hit(&inside, 0.0, 100.0).is_none());
+        assert!(hitable.hit_all(&inside, 0.0, 100.0).is_empty());
+        assert!(hitable.any_hit(&outside, 0.0, 100.0));
+        assert!(!hitable.any_hit(&inside, 0.0, 100.0));
+
+        assert!(!hitable.unwrap().is_one_sided());
+    }
+}
@@ -58,6 +58,24 @@ impl<T> Hitable<T> for Translation<T>
         None
     }
 
+    fn hit_all(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Vec<Hit<T>> {
+        let origin = ray.get_origin() - &self.translation;
+        let direction = Vec3::from_slice(ray.get_direction().get_data());
+        let translated_ray = Ray::from_vec(origin, direction);
+        let mut hits = self.wrapped.hit_all(&translated_ray, t_min, t_max);
+        for hit in hits.iter_mut() {
+            hit.point = &hit.point + &self.translation;
+        }
+        hits
+    }
+
+    fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        let origin = ray.get_origin() - &self.translation;
+        let direction = Vec3::from_slice(ray.get_direction().get_data());
+        let translated_ray = Ray::from_vec(origin, direction);
+        self.wrapped.any_hit(&translated_ray, t_min, t_max)
+    }
+
     fn get_bounds(&self) -> &BoundingBox<T> {
         &self.bounds
     }
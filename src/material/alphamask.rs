@@ -0,0 +1,207 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::float::Float;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::material::{Scatter, Material};
+use crate::texture::Texture;
+
+// How the alpha value read from the mask decides whether a hit is kept
+pub enum AlphaMode<T>
+    where T: Float
+{
+    Threshold(T), // Hits with an alpha below the threshold are skipped
+    // Hits are kept with a probability equal to the alpha.
+    // The decision is a hash of the hit, so that a surface point tested several times
+    // along the same ray (e.g. from different tree leaves) is always kept or always skipped.
+    Stochastic
+}
+
+// Cuts holes into the surface of the wrapped material, e.g. for leaves or fences.
+// The traversal skips the transparent hits and looks for the next surface behind them.
+pub struct AlphaMaskMaterial<T>
+    where T: Float
+{
+    material: Box<dyn Material<T>>,
+    texture: Box<dyn Texture<T>>,
+    mode: AlphaMode<T>
+}
+
+impl<T> AlphaMaskMaterial<T>
+    where T: Float
+{
+    pub fn new(material: Box<dyn Material<T>>, texture: Box<dyn Texture<T>>, mode: AlphaMode<T>) -> Self {
+        AlphaMaskMaterial {
+            material,
+            texture,
+            mode
+        }
+    }
+
+    pub fn get_mode(&self) -> &AlphaMode<T> {
+        &self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: AlphaMode<T>) {
+        self.mode = mode;
+    }
+
+    pub fn get_alpha(&self, hit: &Hit<T>) -> T {
+        // The average of the channels, so that gray scale masks can be used directly
+        let color = self.texture.get_color(hit.u, hit.v, &hit.point);
+        let data = color.get_data();
+        (data[0] + data[1] + data[2]) / T::from(3.0).unwrap()
+    }
+
+    // A pseudo random number in [0, 1) that only depends on the hit
+    fn get_hit_random(hit: &Hit<T>) -> T {
+        let mut hasher = DefaultHasher::new();
+        for value in hit.point.get_data().iter().chain(&[hit.t, hit.u, hit.v]) {
+            value.integer_decode().hash(&mut hasher);
+        }
+        let bits = hasher.finish() >> 11;
+        T::from(bits as f64 / (1u64 << 53) as f64).unwrap()
+    }
+}
+
+impl<T> Material<T> for AlphaMaskMaterial<T>
+    where T: Float
+{
+    fn scatter(&self, incident: &Ray<T>, hit: &Hit<T>) -> Scatter<T> {
+        self.material.scatter(incident, hit)
+    }
+
+    fn is_opaque(&self, hit: &Hit<T>) -> bool {
+        let alpha = self.get_alpha(hit);
+        let opaque = match self.mode {
+            AlphaMode::Threshold(threshold) => alpha >= threshold,
+            AlphaMode::Stochastic => Self::get_hit_random(hit) < alpha
+        };
+        opaque && self.material.is_opaque(hit)
+    }
+
+    fn is_masked(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use crate::vector::Vec3;
+    use crate::actor::Actor;
+    use crate::hitable::primitive::Sphere;
+    use crate::hitable::transform::Translation;
+    use crate::material::plain::PlainMaterial;
+    use crate::texture::uniform::UniformTexture;
+    use crate::tree::Tree;
+    use crate::tree::linear::LinearTree;
+
+    // Opaque below the XY plane, transparent above it
+    struct HalfTexture;
+
+    impl Texture<f64> for HalfTexture {
+        fn get_color(&self, _u: f64, _v: f64, point: &Vec3<f64>) -> Vec3<f64> {
+            let alpha = if point.get_data()[2] < 0.0 { 1.0 } else { 0.0 };
+            Vec3::from_array([alpha, alpha, alpha])
+        }
+    }
+
+    fn make_plain(color: [f64; 3]) -> Box<dyn Material<f64>> {
+        let texture = Box::new(UniformTexture::new(Vec3::from_array(color)));
+        Box::new(PlainMaterial::new(texture))
+    }
+
+    #[test]
+    fn threshold() {
+        let material = AlphaMaskMaterial::new(make_plain([1.0, 0.0, 0.0]), Box::new(HalfTexture), AlphaMode::Threshold(0.5));
+        let actor = Actor::new(Box::new(Sphere::new(1.0)), Box::new(material));
+
+        // The top of the sphere is cut away, the ray goes on to its inner bottom
+        let ray = Ray::from_array([0.0, 0.0, 10.0], [0.0, 0.0, -1.0]);
+        match actor.hit(&ray, 0.0, 100.0) {
+            Some(hit) => {
+                assert_eq!(hit.point.get_data(), [0.0, 0.0, -1.0]);
+                assert_eq!(hit.t, 11.0);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Nothing is left above the cut
+        let ray = Ray::from_array([-10.0, 0.0, 0.5], [1.0, 0.0, 0.0]);
+        assert!(actor.hit(&ray, 0.0, 100.0).is_none());
+    }
+
+    #[test]
+    fn stochastic() {
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.0, 0.0, 0.0])));
+        let material = AlphaMaskMaterial::new(make_plain([1.0, 0.0, 0.0]), texture, AlphaMode::Stochastic);
+        let hit = Hit {
+            point: Vec3::new(),
+            normal: Vec3::from_array([0.0, 0.0, 1.0]),
+            tangent: Vec3::from_array([1.0, 0.0, 0.0]),
+            front_face: true,
+            t: 1.0,
+            u: 0.5,
+            v: 0.5
+        };
+        for _ in 0..100 {
+            assert!(!material.is_opaque(&hit));
+        }
+
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+        let material = AlphaMaskMaterial::new(make_plain([1.0, 0.0, 0.0]), texture, AlphaMode::Stochastic);
+        for _ in 0..100 {
+            assert!(material.is_opaque(&hit));
+        }
+
+        // The same hit always gets the same answer, different hits are kept about half of the time
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        let material = AlphaMaskMaterial::new(make_plain([1.0, 0.0, 0.0]), texture, AlphaMode::Stochastic);
+        let opaque = material.is_opaque(&hit);
+        for _ in 0..100 {
+            assert_eq!(material.is_opaque(&hit), opaque);
+        }
+        let mut hit = hit;
+        let mut kept = 0;
+        for i in 0..1000 {
+            hit.t = 1.0 + i as f64 * 0.001;
+            if material.is_opaque(&hit) {
+                kept += 1;
+            }
+        }
+        assert!(kept > 400 && kept < 600);
+    }
+
+    #[test]
+    fn tree() {
+        // A fully transparent sphere in front of an opaque one
+        let mut tree = LinearTree::new();
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.0, 0.0, 0.0])));
+        let material = AlphaMaskMaterial::new(make_plain([1.0, 0.0, 0.0]), texture, AlphaMode::Threshold(0.5));
+        tree.add_actor(Rc::new(Actor::new(Box::new(Sphere::new(1.0)), Box::new(material))));
+        let hitable = Translation::new(Box::new(Sphere::new(1.0)), Vec3::from_array([0.0, 0.0, -4.0]));
+        tree.add_actor(Rc::new(Actor::new(Box::new(hitable), make_plain([0.0, 1.0, 0.0]))));
+
+        let ray = Ray::from_array([0.0, 0.0, 10.0], [0.0, 0.0, -1.0]);
+        match tree.get_hit(&ray, 0.0, 100.0) {
+            Some((actor, hit)) => {
+                assert_eq!(hit.point.get_data(), [0.0, 0.0, -3.0]);
+                let scatter = actor.material.scatter(&ray, &hit);
+                assert_eq!(scatter.attenuation.get_data(), [0.0, 1.0, 0.0]);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Shadow rays go through the transparent sphere too
+        assert!(tree.any_hit(&ray, 0.0, 100.0));
+        let ray = Ray::from_array([-10.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        assert!(!tree.any_hit(&ray, 0.0, 100.0));
+    }
+}
@@ -33,11 +33,10 @@ impl<T> Material<T> for DielectricMaterial<T>
         let mut outward_normal = &hit.normal * (-T::one());
         let mut n0 = self.n;
         let mut n1 = T::one();
-        let color = self.texture.get_color(T::zero(), T::zero(), &hit.point);
+        let color = self.texture.get_color(hit.u, hit.v, &hit.point);
         let attenuation = Vec3::<T>::from_slice(color.get_data());
-        let c = incident.get_direction().dot(&hit.normal);
 
-        if c < T::zero() {
+        if hit.front_face {
             outward_normal.set_data(hit.normal.get_data());
             n1 = self.n;
             n0 = T::one();
@@ -30,9 +30,9 @@ impl<T> Material<T> for LambertianMaterial<T>
     where T: Float
 {
     fn scatter(&self, incident: &Ray<T>, hit: &Hit<T>) -> Scatter<T> {
-        let color = self.texture.get_color(T::zero(), T::zero(), &hit.point);
+        let color = self.texture.get_color(hit.u, hit.v, &hit.point);
         let attenuation = Vec3::<T>::from_slice(color.get_data()) * self.dimming;
-        let mut normal = Vec3::from_slice(hit.normal.get_data());
+        let mut normal = hit.get_shading_normal();
         normal.normalize();
         let origin = Vec3::from_slice(hit.point.get_data());
         let mut direction = normal + random_point_in_sphere(T::one());
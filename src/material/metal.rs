@@ -30,7 +30,7 @@ impl<T> Material<T> for MetalMaterial<T>
     where T: Float
 {
     fn scatter(&self, incident: &Ray<T>, hit: &Hit<T>) -> Scatter<T> {
-        let color = self.texture.get_color(T::zero(), T::zero(), &hit.point);
+        let color = self.texture.get_color(hit.u, hit.v, &hit.point);
         let attenuation = Vec3::<T>::from_slice(color.get_data());
         let normal = &hit.normal;
         let origin = Vec3::from_slice(hit.point.get_data());
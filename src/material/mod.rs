@@ -1,3 +1,5 @@
+use std::rc::Rc;
+
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
@@ -7,6 +9,8 @@ pub mod plain;
 pub mod lambertian;
 pub mod metal;
 pub mod dielectric;
+pub mod normalmap;
+pub mod alphamask;
 
 pub struct Scatter<T>
     where T: Float
@@ -19,4 +23,29 @@ pub trait Material<T>
     where T: Float
 {
     fn scatter(&self, incident: &Ray<T>, hit: &Hit<T>) -> Scatter<T>;
+    fn is_opaque(&self, _hit: &Hit<T>) -> bool {
+        // Hits on transparent parts of a surface are skipped during the traversal
+        true
+    }
+    fn is_masked(&self) -> bool {
+        // Whether is_opaque can ever return false, otherwise the traversal doesn't need to call it
+        false
+    }
+}
+
+// Lets several actors use the same material
+impl<T> Material<T> for Rc<dyn Material<T>>
+    where T: Float
+{
+    fn scatter(&self, incident: &Ray<T>, hit: &Hit<T>) -> Scatter<T> {
+        (**self).scatter(incident, hit)
+    }
+
+    fn is_opaque(&self, hit: &Hit<T>) -> bool {
+        (**self).is_opaque(hit)
+    }
+
+    fn is_masked(&self) -> bool {
+        (**self).is_masked()
+    }
 }
@@ -0,0 +1,199 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::material::{Scatter, Material};
+use crate::texture::Texture;
+
+// Decorators that perturb the shading normal before scattering with the wrapped material.
+// Both rely on the tangent frame and surface coordinates reported by the hitables.
+
+// The texture colors are tangent space normals, mapped from [-1, 1] to [0, 1]
+pub struct NormalMapMaterial<T>
+    where T: Float
+{
+    material: Box<dyn Material<T>>,
+    texture: Box<dyn Texture<T>>
+}
+
+impl<T> NormalMapMaterial<T>
+    where T: Float
+{
+    pub fn new(material: Box<dyn Material<T>>, texture: Box<dyn Texture<T>>) -> Self {
+        NormalMapMaterial {
+            material,
+            texture
+        }
+    }
+}
+
+impl<T> Material<T> for NormalMapMaterial<T>
+    where T: Float
+{
+    fn scatter(&self, incident: &Ray<T>, hit: &Hit<T>) -> Scatter<T> {
+        let color = self.texture.get_color(hit.u, hit.v, &hit.point);
+        let two = T::from(2.0).unwrap();
+        let x = two * color.get_data()[0] - T::one();
+        let y = two * color.get_data()[1] - T::one();
+        let z = two * color.get_data()[2] - T::one();
+
+        let mut normal = &hit.tangent * x + hit.get_bitangent() * y + &hit.normal * z;
+        normal.normalize();
+        let mut hit = hit.clone();
+        hit.normal = normal;
+        self.material.scatter(incident, &hit)
+    }
+
+    fn is_opaque(&self, hit: &Hit<T>) -> bool {
+        self.material.is_opaque(hit)
+    }
+
+    fn is_masked(&self) -> bool {
+        self.material.is_masked()
+    }
+}
+
+// The texture is a height map, its gradient along u and v tilts the normal
+pub struct BumpMapMaterial<T>
+    where T: Float
+{
+    material: Box<dyn Material<T>>,
+    texture: Box<dyn Texture<T>>,
+    strength: T,
+    delta: T // Step in surface coordinates used to differentiate the heights
+}
+
+impl<T> BumpMapMaterial<T>
+    where T: Float
+{
+    pub fn new(material: Box<dyn Material<T>>, texture: Box<dyn Texture<T>>, strength: T) -> Self {
+        BumpMapMaterial {
+            material,
+            texture,
+            strength,
+            delta: T::from(0.001).unwrap()
+        }
+    }
+
+    pub fn get_strength(&self) -> T {
+        self.strength
+    }
+
+    pub fn set_strength(&mut self, strength: T) {
+        self.strength = strength;
+    }
+
+    pub fn get_delta(&self) -> T {
+        self.delta
+    }
+
+    pub fn set_delta(&mut self, delta: T) {
+        self.delta = delta;
+    }
+
+    fn get_height(&self, u: T, v: T, point: &Vec3<T>) -> T {
+        // The average of the channels, so that gray scale textures can be used directly
+        let color = self.texture.get_color(u, v, point);
+        let data = color.get_data();
+        (data[0] + data[1] + data[2]) / T::from(3.0).unwrap()
+    }
+}
+
+impl<T> Material<T> for BumpMapMaterial<T>
+    where T: Float
+{
+    fn scatter(&self, incident: &Ray<T>, hit: &Hit<T>) -> Scatter<T> {
+        let h = self.get_height(hit.u, hit.v, &hit.point);
+        let du = (self.get_height(hit.u + self.delta, hit.v, &hit.point) - h) / self.delta;
+        let dv = (self.get_height(hit.u, hit.v + self.delta, &hit.point) - h) / self.delta;
+
+        let mut normal = &hit.normal - (&hit.tangent * du + hit.get_bitangent() * dv) * self.strength;
+        normal.normalize();
+        let mut hit = hit.clone();
+        hit.normal = normal;
+        self.material.scatter(incident, &hit)
+    }
+
+    fn is_opaque(&self, hit: &Hit<T>) -> bool {
+        self.material.is_opaque(hit)
+    }
+
+    fn is_masked(&self) -> bool {
+        self.material.is_masked()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::metal::MetalMaterial;
+    use crate::texture::uniform::UniformTexture;
+
+    struct RampTexture;
+
+    impl Texture<f64> for RampTexture {
+        fn get_color(&self, u: f64, _v: f64, _point: &Vec3<f64>) -> Vec3<f64> {
+            Vec3::from_array([u, u, u])
+        }
+    }
+
+    fn make_hit() -> Hit<f64> {
+        Hit {
+            point: Vec3::new(),
+            normal: Vec3::from_array([0.0, 0.0, 1.0]),
+            tangent: Vec3::from_array([1.0, 0.0, 0.0]),
+            front_face: true,
+            t: 1.0,
+            u: 0.5,
+            v: 0.5
+        }
+    }
+
+    fn make_mirror() -> Box<dyn Material<f64>> {
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+        Box::new(MetalMaterial::new(texture, 0.0))
+    }
+
+    fn get_direction(scatter: Scatter<f64>) -> Vec3<f64> {
+        match scatter.scattered {
+            Some(ray) => Vec3::from_slice(ray.get_direction().get_data()),
+            None => panic!("The mirror should scatter")
+        }
+    }
+
+    #[test]
+    fn normal_map() {
+        let ray = Ray::from_array([0.0, 0.0, 1.0], [0.0, 0.0, -1.0]);
+        let hit = make_hit();
+
+        // A flat normal map leaves the normal untouched
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 1.0])));
+        let material = NormalMapMaterial::new(make_mirror(), texture);
+        let direction = get_direction(material.scatter(&ray, &hit));
+        assert_eq!(direction.get_data(), [0.0, 0.0, 1.0]);
+
+        // Tilting the normal towards the tangent by 45 degrees sends the ray along it
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.5, 1.0])));
+        let material = NormalMapMaterial::new(make_mirror(), texture);
+        let direction = get_direction(material.scatter(&ray, &hit));
+        assert!((direction.get_data()[0] - 1.0).abs() < 1e-12);
+        assert!(direction.get_data()[2].abs() < 1e-12);
+    }
+
+    #[test]
+    fn bump_map() {
+        let ray = Ray::from_array([0.0, 0.0, 1.0], [0.0, 0.0, -1.0]);
+        let hit = make_hit();
+
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.3, 0.3, 0.3])));
+        let material = BumpMapMaterial::new(make_mirror(), texture, 1.0);
+        let direction = get_direction(material.scatter(&ray, &hit));
+        assert_eq!(direction.get_data(), [0.0, 0.0, 1.0]);
+
+        // Heights rising along u tilt the normal against the tangent
+        let material = BumpMapMaterial::new(make_mirror(), Box::new(RampTexture), 1.0);
+        let direction = get_direction(material.scatter(&ray, &hit));
+        assert!((direction.get_data()[0] + 1.0).abs() < 1e-6);
+        assert!(direction.get_data()[2].abs() < 1e-6);
+    }
+}
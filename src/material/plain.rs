@@ -25,7 +25,7 @@ impl<T> Material<T> for PlainMaterial<T>
     where T: Float
 {
     fn scatter(&self, _incident: &Ray<T>, hit: &Hit<T>) -> Scatter<T> {
-        let color = self.texture.get_color(T::zero(), T::zero(), &hit.point);
+        let color = self.texture.get_color(hit.u, hit.v, &hit.point);
         let attenuation = Vec3::<T>::from_slice(color.get_data());
         Scatter::<T> {
             attenuation,
@@ -7,7 +7,10 @@ use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::hitable::Hitable;
 use crate::camera::Camera;
-use crate::scene::Scene;
+use crate::camera::animation::CameraAnimation;
+use crate::scene::{Scene, SceneHit};
+#[cfg(feature = "stats")]
+use crate::tree::stats;
 
 pub struct Image<T>
     where T: Float
@@ -28,6 +31,24 @@ impl<T> Image<T>
             data
         }
     }
+
+    pub fn side_by_side(images: &[Image<T>]) -> Self {
+        // Concatenate images of the same height horizontally, from left to right
+        let height = images.first().map_or(0, |image| image.height);
+        let width = images.iter().map(|image| image.width).sum();
+        let mut result = Image::<T>::new(width, height);
+        for j in 0..height {
+            let mut offset = 0;
+            for image in images.iter() {
+                assert_eq!(image.height, height);
+                let src = 3 * j * image.width;
+                let dst = 3 * (j * width + offset);
+                result.data[dst..dst + 3 * image.width].copy_from_slice(&image.data[src..src + 3 * image.width]);
+                offset += image.width;
+            }
+        }
+        result
+    }
 }
 
 pub struct Renderer {
@@ -81,6 +102,14 @@ impl Renderer {
         color
     }
 
+    // The actor seen through the pixel, e.g. to select it in a viewer
+    pub fn pick<T>(&self, i: usize, j: usize, scene: &Scene<T>, camera: &dyn Camera<T>) -> Option<SceneHit<T>>
+        where T: Float
+    {
+        let ray = self.get_pixel_ray(i, j, camera);
+        scene.intersect(&ray)
+    }
+
     pub fn render<T>(&self, scene: &Scene<T>, camera: &Camera<T>) -> Image<T>
         where T: Float
     {
@@ -97,6 +126,69 @@ impl Renderer {
         image
     }
 
+    // Colors each pixel by the work done by the tree to render it (box and actor tests),
+    // from blue for none to red for the most expensive pixel. Resets the traversal counters.
+    #[cfg(feature = "stats")]
+    pub fn render_heatmap<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>) -> Image<T>
+        where T: Float
+    {
+        let mut costs = Vec::with_capacity(self.width * self.height);
+        for j in 0..self.height {
+            for i in 0..self.width {
+                stats::reset_counters();
+                self.render_pixel(i, j, scene, camera);
+                costs.push(stats::get_counters().get_cost());
+            }
+        }
+        stats::reset_counters();
+
+        let max_cost = costs.iter().cloned().max().unwrap_or(0);
+        let max_cost = T::from(if max_cost > 0 { max_cost } else { 1 }).unwrap();
+        let two = T::from(2.0).unwrap();
+        let mut image = Image::<T>::new(self.width, self.height);
+        for (index, &cost) in costs.iter().enumerate() {
+            let cost = T::from(cost).unwrap() / max_cost;
+            let color = if cost < T::from(0.5).unwrap() {
+                [T::zero(), two * cost, T::one() - two * cost]
+            } else {
+                [two * cost - T::one(), two - two * cost, T::zero()]
+            };
+            image.data[3 * index..3 * index + 3].copy_from_slice(&color);
+        }
+        image
+    }
+
+    pub fn render_views<T, C>(&self, scene: &Scene<T>, cameras: &[C]) -> Vec<Image<T>>
+        where T: Float, C: Camera<T>
+    {
+        cameras.iter().map(|camera| self.render(scene, camera)).collect()
+    }
+
+    pub fn render_side_by_side<T, C>(&self, scene: &Scene<T>, cameras: &[C]) -> Image<T>
+        where T: Float, C: Camera<T>
+    {
+        let images = self.render_views(scene, cameras);
+        Image::side_by_side(&images)
+    }
+
+    // Render a sequence of frames evenly spaced over the duration of the animation.
+    // The first and last frames are at the start and end of the animation, except for looping
+    // animations where the last frame stops short of the end so that the first one isn't repeated.
+    // Each frame is handed to the output callback together with its number.
+    pub fn render_sequence<T, C, A, F>(&self, scene: &Scene<T>, camera: &mut C, animation: &A, frames: usize, mut output: F)
+        where T: Float, C: Camera<T>, A: CameraAnimation<T>, F: FnMut(usize, Image<T>)
+    {
+        let duration = animation.get_duration();
+        let intervals = if animation.is_looping() { frames } else { frames.saturating_sub(1) };
+        let intervals = T::from(intervals.max(1)).unwrap();
+        for i in 0..frames {
+            let time = duration * T::from(i).unwrap() / intervals;
+            animation.apply(camera, time);
+            let image = self.render(scene, camera);
+            output(i, image);
+        }
+    }
+
     fn get_ray<T>(&self, i: usize, j: usize, camera: &Camera<T>, two: T) -> Ray<T>
         where T: Float
     {
@@ -105,9 +197,7 @@ impl Renderer {
         match self.antialiasing {
             // If antialiasing is disabled, the ray always hits the pixel in the same position
             false => {
-                let v = two * (T::from(j).unwrap() / T::from(self.height).unwrap()) - T::one();
-                let u = two * (T::from(i).unwrap() / T::from(self.width).unwrap()) - T::one();
-                camera.get_ray(u, v)
+                self.get_pixel_ray(i, j, camera)
             },
             // If antializasing is enabled, the ray is randomly chosen in the vicinity of the pixel
             true => {
@@ -119,4 +209,175 @@ impl Renderer {
             }
         }
     }
+
+    fn get_pixel_ray<T>(&self, i: usize, j: usize, camera: &dyn Camera<T>) -> Ray<T>
+        where T: Float
+    {
+        let two = T::from(2.0).unwrap();
+        let v = two * (T::from(j).unwrap() / T::from(self.height).unwrap()) - T::one();
+        let u = two * (T::from(i).unwrap() / T::from(self.width).unwrap()) - T::one();
+        camera.get_ray(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::Actor;
+    use crate::hitable::primitive::Sphere;
+    use crate::material::plain::PlainMaterial;
+    use crate::texture::uniform::UniformTexture;
+    use crate::camera::perspective::PerspectiveCamera;
+    use crate::camera::animation::{KeyframeAnimation, Keyframe, Interpolation, Turntable};
+
+    // Remembers where the animation moved it
+    struct RecordingCamera {
+        camera: PerspectiveCamera<f64>,
+        positions: Vec<[f64; 3]>
+    }
+
+    impl Camera<f64> for RecordingCamera {
+        fn get_position(&self) -> &Vec3<f64> { self.camera.get_position() }
+        fn set_position(&mut self, position: &[f64]) {
+            self.positions.push([position[0], position[1], position[2]]);
+            self.camera.set_position(position);
+        }
+        fn get_direction(&self) -> &Vec3<f64> { self.camera.get_direction() }
+        fn set_direction(&mut self, direction: &[f64]) { self.camera.set_direction(direction) }
+        fn get_lookat(&self) -> &Vec3<f64> { self.camera.get_lookat() }
+        fn set_lookat(&mut self, lookat: &[f64]) { self.camera.set_lookat(lookat) }
+        fn get_up(&self) -> &Vec3<f64> { self.camera.get_up() }
+        fn set_up(&mut self, up: &[f64]) { self.camera.set_up(up) }
+        fn get_aperture(&self) -> f64 { self.camera.get_aperture() }
+        fn set_aperture(&mut self, aperture: f64) { self.camera.set_aperture(aperture) }
+        fn get_focus(&self) -> f64 { self.camera.get_focus() }
+        fn set_focus(&mut self, focus: f64) { self.camera.set_focus(focus) }
+        fn get_aspect(&self) -> f64 { self.camera.get_aspect() }
+        fn set_aspect(&mut self, aspect: f64) { self.camera.set_aspect(aspect) }
+        fn get_fov(&self) -> f64 { self.camera.get_fov() }
+        fn set_fov(&mut self, fov: f64) { self.camera.set_fov(fov) }
+        fn get_ray(&self, r: f64, s: f64) -> Ray<f64> { self.camera.get_ray(r, s) }
+    }
+
+    fn record_sequence<A>(animation: &A, frames: usize) -> (Vec<usize>, Vec<[f64; 3]>)
+        where A: CameraAnimation<f64>
+    {
+        let scene = make_scene();
+        let renderer = Renderer::new(1, 1, 1, 0, false);
+        let mut camera = RecordingCamera { camera: PerspectiveCamera::new(), positions: Vec::new() };
+        let mut numbers = Vec::new();
+        renderer.render_sequence(&scene, &mut camera, animation, frames, |i, image| {
+            assert_eq!((image.width, image.height), (1, 1));
+            numbers.push(i);
+        });
+        (numbers, camera.positions)
+    }
+
+    fn get_pixel(image: &Image<f64>, i: usize, j: usize) -> &[f64] {
+        let index = 3 * (j * image.width + i);
+        &image.data[index..index + 3]
+    }
+
+    fn make_scene() -> Scene<f64> {
+        // A red sphere at the origin on a blue background
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.0, 0.0, 1.0]));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.0, 0.0])));
+        scene.add_actor(Actor::new(Box::new(Sphere::new(5.0)), Box::new(PlainMaterial::new(texture))));
+        scene
+    }
+
+    fn make_camera(direction: [f64; 3]) -> PerspectiveCamera<f64> {
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0.0, 0.0, 10.0]);
+        camera.set_direction(&direction);
+        camera
+    }
+
+    #[test]
+    fn side_by_side() {
+        let mut left = Image::<f64>::new(2, 2);
+        let mut right = Image::<f64>::new(3, 2);
+        for (index, value) in left.data.iter_mut().enumerate() {
+            *value = index as f64;
+        }
+        for (index, value) in right.data.iter_mut().enumerate() {
+            *value = 100.0 + index as f64;
+        }
+
+        let image = Image::side_by_side(&[left, right]);
+        assert_eq!((image.width, image.height), (5, 2));
+        assert_eq!(get_pixel(&image, 0, 0), [0.0, 1.0, 2.0]);
+        assert_eq!(get_pixel(&image, 1, 1), [9.0, 10.0, 11.0]);
+        assert_eq!(get_pixel(&image, 2, 0), [100.0, 101.0, 102.0]);
+        assert_eq!(get_pixel(&image, 4, 0), [106.0, 107.0, 108.0]);
+        assert_eq!(get_pixel(&image, 2, 1), [109.0, 110.0, 111.0]);
+        assert_eq!(get_pixel(&image, 4, 1), [115.0, 116.0, 117.0]);
+
+        let image = Image::<f64>::side_by_side(&[]);
+        assert_eq!((image.width, image.height), (0, 0));
+    }
+
+    #[test]
+    fn views() {
+        let scene = make_scene();
+        let renderer = Renderer::new(4, 3, 1, 0, false);
+        // The first camera looks at the sphere, the second one away from it
+        let cameras = [make_camera([0.0, 0.0, -1.0]), make_camera([0.0, 0.0, 1.0])];
+
+        let images = renderer.render_views(&scene, &cameras);
+        assert_eq!(images.len(), 2);
+        for image in &images {
+            assert_eq!((image.width, image.height), (4, 3));
+        }
+        assert_eq!(get_pixel(&images[0], 2, 1), [1.0, 0.0, 0.0]);
+        assert_eq!(get_pixel(&images[0], 0, 0), [0.0, 0.0, 1.0]);
+        assert_eq!(get_pixel(&images[1], 2, 1), [0.0, 0.0, 1.0]);
+
+        let image = renderer.render_side_by_side(&scene, &cameras);
+        assert_eq!((image.width, image.height), (8, 3));
+        assert_eq!(image.data, Image::side_by_side(&images).data);
+        assert_eq!(get_pixel(&image, 2, 1), [1.0, 0.0, 0.0]);
+        assert_eq!(get_pixel(&image, 6, 1), [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn sequence() {
+        let keyframe = |time: f64, x: f64| Keyframe {
+            time,
+            position: Vec3::from_array([x, -10.0, 0.0]),
+            lookat: Vec3::from_array([x, 0.0, 0.0]),
+            fov: 1.0,
+            focus: 10.0
+        };
+        let mut animation = KeyframeAnimation::new(Interpolation::Linear);
+        animation.add_keyframe(keyframe(0.0, 0.0));
+        animation.add_keyframe(keyframe(2.0, 4.0));
+
+        // The last frame of an open animation is its last keyframe
+        let (numbers, positions) = record_sequence(&animation, 5);
+        assert_eq!(numbers, [0, 1, 2, 3, 4]);
+        let expected: Vec<[f64; 3]> = (0..5).map(|i| [i as f64, -10.0, 0.0]).collect();
+        assert_eq!(positions, expected);
+
+        let (numbers, positions) = record_sequence(&animation, 1);
+        assert_eq!(numbers, [0]);
+        assert_eq!(positions, [[0.0, -10.0, 0.0]]);
+
+        let (numbers, positions) = record_sequence(&animation, 0);
+        assert!(numbers.is_empty());
+        assert!(positions.is_empty());
+
+        // A looping animation stops a frame short of coming back to the start
+        let turntable = Turntable::new(Vec3::new(), Vec3::from_array([0.0, -10.0, 0.0]), Vec3::from_array([0.0, 0.0, 1.0]), 2.0);
+        let (numbers, positions) = record_sequence(&turntable, 4);
+        assert_eq!(numbers, [0, 1, 2, 3]);
+        let expected = [[0.0, -10.0, 0.0], [10.0, 0.0, 0.0], [0.0, 10.0, 0.0], [-10.0, 0.0, 0.0]];
+        for (position, expected) in positions.iter().zip(expected.iter()) {
+            for i in 0..3 {
+                assert!((position[i] - expected[i]).abs() < 1e-12);
+            }
+        }
+        assert_eq!(positions.len(), 4);
+    }
 }
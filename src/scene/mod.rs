@@ -1,24 +1,47 @@
 use std::rc::Rc;
+use std::collections::{HashMap, BTreeMap};
 
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
+use crate::hit::Hit;
 use crate::actor::Actor;
+use crate::hitable::Hitable;
+use crate::material::Material;
 use crate::boundingbox::BoundingBox;
 use crate::tree::{Tree, TreeType};
 use crate::tree::linear::LinearTree;
 use crate::tree::binary::BinaryTree;
-use crate::tree::oct::Octree;
+use crate::tree::kd::KdTree;
+use crate::tree::grid::GridTree;
+use crate::tree::oct::{Octree, OctreeOptions};
+use crate::tree::stats::TreeStats;
 
+// Stable handle to an actor in the scene, still valid after other actors are removed
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct ActorId(usize);
+
+// The result of a scene query
+pub struct SceneHit<T>
+    where T: Float
+{
+    pub actor: ActorId,
+    pub hit: Hit<T>,
+    pub distance: T // From the ray origin, independent of the length of the ray direction
+}
 
 pub struct Scene<T>
     where T: Float
 {
-    actors: Vec<Rc<Actor<T>>>,
+    actors: BTreeMap<ActorId, Rc<Actor<T>>>, // Sorted by id, i.e. in the order they were added
+    actor_ids: HashMap<*const Actor<T>, ActorId>, // To find the handle of the actors returned by the tree
+    next_id: usize,
     background: Vec3<T>,
     bounds: BoundingBox<T>,
     tree: Box<dyn Tree<T>>,
-    tree_type: TreeType
+    tree_type: TreeType,
+    compact_tree: bool, // Whether the tree is flattened after being built
+    octree_options: OctreeOptions<T>
 }
 
 impl<T> Scene<T>
@@ -26,11 +49,15 @@ impl<T> Scene<T>
 {
     pub fn new() -> Self {
         Scene {
-            actors: vec!(),
+            actors: BTreeMap::new(),
+            actor_ids: HashMap::new(),
+            next_id: 0,
             background: Vec3::<T>::new(),
             bounds: BoundingBox::<T>::new(Vec3::<T>::new(), Vec3::<T>::new()),
             tree: Box::new(LinearTree::new()),
-            tree_type: TreeType::Linear
+            tree_type: TreeType::Linear,
+            compact_tree: false,
+            octree_options: OctreeOptions::new()
         }
     }
 
@@ -38,10 +65,74 @@ impl<T> Scene<T>
         self.background = background;
     }
 
-    pub fn add_actor(&mut self, actor: Actor<T>) {
-        let _expanded = self.bounds.expand(&actor.hitable.get_bounds());
+    pub fn add_actor(&mut self, actor: Actor<T>) -> ActorId {
+        let id = ActorId(self.next_id);
+        self.next_id += 1;
         let actor = Rc::new(actor);
-        self.actors.push(Rc::clone(&actor));
+        self.actors.insert(id, Rc::clone(&actor));
+        self.actor_ids.insert(Rc::as_ptr(&actor), id);
+        self.insert_actor(actor);
+        id
+    }
+
+    pub fn get_actor(&self, id: ActorId) -> Option<&Actor<T>> {
+        self.actors.get(&id).map(|actor| &**actor)
+    }
+
+    pub fn remove_actor(&mut self, id: ActorId) -> bool {
+        let actor = match self.actors.remove(&id) {
+            Some(actor) => actor,
+            None => return false
+        };
+        self.actor_ids.remove(&Rc::as_ptr(&actor));
+        self.tree.remove_actor(&actor);
+        self.tree.refit();
+        self.update_bounds();
+        true
+    }
+
+    pub fn replace_material(&mut self, id: ActorId, material: Box<dyn Material<T>>) -> bool {
+        self.update_actor(id, |actor| actor.material = material)
+    }
+
+    pub fn set_hitable(&mut self, id: ActorId, hitable: Box<dyn Hitable<T>>) -> bool {
+        self.update_actor(id, |actor| {
+            // One-sided actors stay one-sided with their new hitable
+            let one_sided = actor.is_one_sided();
+            actor.hitable = hitable;
+            if one_sided {
+                actor.set_one_sided(true);
+            }
+        })
+    }
+
+    pub fn intersect(&self, ray: &Ray<T>) -> Option<SceneHit<T>> {
+        let (t_min, t_max) = Scene::<T>::get_ray_range();
+        let (actor, hit) = self.tree.get_hit(ray, t_min, t_max)?;
+        let actor = *self.actor_ids.get(&Rc::as_ptr(&actor))?;
+        let distance = hit.t * ray.get_direction().norm();
+        Some(SceneHit {
+            actor,
+            hit,
+            distance
+        })
+    }
+
+    // Whether anything blocks the ray before t_max, e.g. for shadow rays
+    pub fn occluded(&self, ray: &Ray<T>, t_max: T) -> bool {
+        let (t_min, _) = Scene::<T>::get_ray_range();
+        self.tree.any_hit(ray, t_min, t_max)
+    }
+
+    fn get_ray_range() -> (T, T) {
+        (T::from(0.000000001).unwrap(), T::from(10000000000.0).unwrap())
+    }
+
+    fn insert_actor(&mut self, actor: Rc<Actor<T>>) {
+        // Unbounded actors (e.g. planes) are handled separately by the trees
+        if actor.hitable.get_bounds().is_finite() {
+            self.bounds.expand(actor.hitable.get_bounds());
+        }
         let success = self.tree.add_actor(actor);
 
         if !success {
@@ -49,8 +140,46 @@ impl<T> Scene<T>
         }
     }
 
+    fn update_actor<F>(&mut self, id: ActorId, update: F) -> bool
+        where F: FnOnce(&mut Actor<T>)
+    {
+        let mut actor = match self.actors.remove(&id) {
+            Some(actor) => actor,
+            None => return false
+        };
+        // The actor can only be edited once the tree doesn't reference it anymore.
+        // If the tree kept some references, a new tree is built without the actor.
+        self.tree.remove_actor(&actor);
+        if Rc::get_mut(&mut actor).is_none() {
+            self.rebuild_tree();
+        }
+        let updated = match Rc::get_mut(&mut actor) {
+            Some(actor) => {
+                update(actor);
+                true
+            },
+            None => false
+        };
+
+        self.actors.insert(id, Rc::clone(&actor));
+        self.tree.refit();
+        self.update_bounds();
+        self.insert_actor(actor);
+        updated
+    }
+
+    fn update_bounds(&mut self) {
+        self.bounds = BoundingBox::<T>::new(Vec3::<T>::new(), Vec3::<T>::new());
+        for actor in self.actors.values() {
+            if actor.hitable.get_bounds().is_finite() {
+                self.bounds.expand(actor.hitable.get_bounds());
+            }
+        }
+    }
+
     pub fn get_color(&self, ray: &Ray<T>, reflection: usize, max_reflection: usize) -> Vec3<T> {
-        let current_hit = self.tree.get_hit(ray, T::from(0.000000001).unwrap(), T::from(10000000000.0).unwrap());
+        let (t_min, t_max) = Scene::<T>::get_ray_range();
+        let current_hit = self.tree.get_hit(ray, t_min, t_max);
 
         match current_hit {
             Some((actor, hit)) => {
@@ -82,6 +211,24 @@ impl<T> Scene<T>
         self.rebuild_tree();
     }
 
+    // A compact tree is faster to traverse, but adding actors to it rebuilds it.
+    // Grids and kd-trees are never flattened.
+    pub fn set_compact_tree(&mut self, compact_tree: bool) {
+        self.compact_tree = compact_tree;
+        self.rebuild_tree();
+    }
+
+    pub fn set_octree_options(&mut self, options: OctreeOptions<T>) {
+        self.octree_options = options;
+        self.rebuild_tree();
+    }
+
+    // The shape of the current tree, e.g. to compare tree types.
+    // The work done while rendering is in the traversal counters of tree::stats.
+    pub fn get_tree_stats(&self) -> TreeStats {
+        self.tree.get_stats()
+    }
+
     fn rebuild_tree(&mut self) {
         let mut tree: Box<dyn Tree<T>> = match self.tree_type {
             TreeType::Linear => {
@@ -101,15 +248,185 @@ impl<T> Scene<T>
                 for i in 0..3 {
                     tree_bounds.pad_axis(pad, i);
                 }
-                Box::new(Octree::<T>::new(tree_bounds))
+                Box::new(Octree::<T>::with_options(tree_bounds, self.octree_options))
+            },
+            TreeType::Kd => {
+                Box::new(KdTree::new())
+            },
+            TreeType::Grid => {
+                Box::new(GridTree::new())
             }
         };
 
-        for i in 0..self.actors.len() {
-            let actor = Rc::clone(&self.actors[i]);
-            tree.add_actor(actor);
+        for actor in self.actors.values() {
+            tree.add_actor(Rc::clone(actor));
         }
+        // Trees built as the actors come in can be built again once they are all known
+        tree.rebuild();
 
+        // The grid already keeps its cells in flat arrays, a flattened copy of it is slower.
+        // The kd-tree traversal already skips the box tests, its flattened copy is no faster.
+        let flatten = !matches!(self.tree_type, TreeType::Grid | TreeType::Kd);
+        if self.compact_tree && flatten {
+            tree = Box::new(tree.flatten());
+        }
         self.tree = tree;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::primitive::Sphere;
+    use crate::hitable::transform::Translation;
+    use crate::material::plain::PlainMaterial;
+    use crate::texture::uniform::UniformTexture;
+    use crate::camera::perspective::PerspectiveCamera;
+    use crate::renderer::Renderer;
+
+    fn make_sphere(x: f64) -> Box<dyn Hitable<f64>> {
+        Box::new(Translation::new(Box::new(Sphere::new(1.0)), Vec3::from_array([x, 0.0, 0.0])))
+    }
+
+    fn make_plain(color: [f64; 3]) -> Box<dyn Material<f64>> {
+        let texture = Box::new(UniformTexture::new(Vec3::from_array(color)));
+        Box::new(PlainMaterial::new(texture))
+    }
+
+    fn edit(tree_type: TreeType, compact_tree: bool) {
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.0, 0.0, 1.0]));
+        scene.set_tree_type(tree_type);
+        scene.set_compact_tree(compact_tree);
+        let ids: Vec<ActorId> = (0..4)
+            .map(|i| scene.add_actor(Actor::new(make_sphere(4.0 * i as f64), make_plain([1.0, 0.0, 0.0]))))
+            .collect();
+
+        let ray = Ray::from_array([4.0, 0.0, 10.0], [0.0, 0.0, -1.0]);
+        assert_eq!(scene.get_color(&ray, 0, 0).get_data(), [1.0, 0.0, 0.0]);
+
+        assert!(scene.replace_material(ids[1], make_plain([0.0, 1.0, 0.0])));
+        assert_eq!(scene.get_color(&ray, 0, 0).get_data(), [0.0, 1.0, 0.0]);
+
+        // Moving the actor away, then another one in its place
+        assert!(scene.set_hitable(ids[1], make_sphere(-20.0)));
+        assert_eq!(scene.get_color(&ray, 0, 0).get_data(), [0.0, 0.0, 1.0]);
+        let moved = Ray::from_array([-20.0, 0.0, 10.0], [0.0, 0.0, -1.0]);
+        assert_eq!(scene.get_color(&moved, 0, 0).get_data(), [0.0, 1.0, 0.0]);
+        assert!(scene.set_hitable(ids[3], make_sphere(4.0)));
+        assert_eq!(scene.get_color(&ray, 0, 0).get_data(), [1.0, 0.0, 0.0]);
+
+        // The handles of the other actors stay valid
+        assert!(scene.remove_actor(ids[3]));
+        assert!(!scene.remove_actor(ids[3]));
+        assert_eq!(scene.get_color(&ray, 0, 0).get_data(), [0.0, 0.0, 1.0]);
+        assert!(scene.get_actor(ids[0]).is_some());
+        assert!(scene.get_actor(ids[3]).is_none());
+        let ray = Ray::from_array([0.0, 0.0, 10.0], [0.0, 0.0, -1.0]);
+        assert_eq!(scene.get_color(&ray, 0, 0).get_data(), [1.0, 0.0, 0.0]);
+
+        // A large actor, e.g. in several nodes of the kd-tree or with the large actors of the grid
+        let hitable = Box::new(Translation::new(Box::new(Sphere::new(6.0)), Vec3::from_array([6.0, 10.0, -10.0])));
+        let large = scene.add_actor(Actor::new(hitable, make_plain([1.0, 0.0, 0.0])));
+        let ray = Ray::from_array([6.0, 10.0, 10.0], [0.0, 0.0, -1.0]);
+        assert_eq!(scene.get_color(&ray, 0, 0).get_data(), [1.0, 0.0, 0.0]);
+        assert!(scene.replace_material(large, make_plain([0.0, 1.0, 0.0])));
+        assert_eq!(scene.get_color(&ray, 0, 0).get_data(), [0.0, 1.0, 0.0]);
+        assert!(scene.set_hitable(large, make_sphere(40.0)));
+        assert_eq!(scene.get_color(&ray, 0, 0).get_data(), [0.0, 0.0, 1.0]);
+        assert!(scene.remove_actor(large));
+        assert!(!scene.replace_material(large, make_plain([0.0, 1.0, 0.0])));
+    }
+
+    #[test]
+    fn edit_linear() {
+        edit(TreeType::Linear, false);
+    }
+
+    #[test]
+    fn edit_binary() {
+        edit(TreeType::Binary, false);
+    }
+
+    #[test]
+    fn edit_oct() {
+        edit(TreeType::Oct, false);
+    }
+
+    #[test]
+    fn edit_kd() {
+        edit(TreeType::Kd, false);
+    }
+
+    #[test]
+    fn edit_grid() {
+        edit(TreeType::Grid, false);
+    }
+
+    #[test]
+    fn edit_compact() {
+        edit(TreeType::Linear, true);
+        edit(TreeType::Binary, true);
+        edit(TreeType::Oct, true);
+        edit(TreeType::Kd, true);
+        edit(TreeType::Grid, true);
+    }
+
+    #[test]
+    fn query() {
+        let mut scene = Scene::<f64>::new();
+        scene.set_tree_type(TreeType::Binary);
+        let near = scene.add_actor(Actor::new(make_sphere(0.0), make_plain([1.0, 0.0, 0.0])));
+        let far = scene.add_actor(Actor::new(make_sphere(-6.0), make_plain([1.0, 0.0, 0.0])));
+
+        // The distance doesn't depend on the length of the direction
+        let ray = Ray::from_array([5.0, 0.0, 0.0], [-2.0, 0.0, 0.0]);
+        match scene.intersect(&ray) {
+            Some(result) => {
+                assert_eq!(result.actor, near);
+                assert_eq!(result.hit.t, 2.0);
+                assert_eq!(result.distance, 4.0);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        // Removing the closest actor reveals the one behind it
+        assert!(scene.remove_actor(near));
+        match scene.intersect(&ray) {
+            Some(result) => {
+                assert_eq!(result.actor, far);
+                assert_eq!(result.distance, 10.0);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+
+        assert!(scene.occluded(&ray, 100.0));
+        assert!(!scene.occluded(&ray, 4.0));
+        let ray = Ray::from_array([5.0, 5.0, 0.0], [-1.0, 0.0, 0.0]);
+        assert!(!scene.occluded(&ray, 100.0));
+    }
+
+    #[test]
+    fn pick() {
+        let mut scene = Scene::<f64>::new();
+        let hitable = Box::new(Translation::new(Box::new(Sphere::new(1.0)), Vec3::from_array([0.0, 0.0, -5.0])));
+        let id = scene.add_actor(Actor::new(hitable, make_plain([1.0, 0.0, 0.0])));
+        let camera = PerspectiveCamera::<f64>::new();
+        let renderer = Renderer::new(10, 10, 0, 0, false);
+
+        match renderer.pick(5, 5, &scene, &camera) {
+            Some(result) => {
+                assert_eq!(result.actor, id);
+                assert_eq!(result.distance, 4.0);
+            },
+            None => {
+                assert!(false);
+            }
+        }
+        assert!(renderer.pick(0, 0, &scene, &camera).is_none());
+    }
+}
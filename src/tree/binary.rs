@@ -7,13 +7,16 @@ use crate::hit::Hit;
 use crate::boundingbox::BoundingBox;
 use crate::actor::Actor;
 use crate::tree::Tree;
+use crate::tree::flat::{FlatTree, FlatSource};
+use crate::tree::stats::{self, TreeStats};
 
 pub struct BinaryTree<T>
     where T: Float
 {
     bounds: BoundingBox<T>, // The bounds of this node
     pub children: [Option<Box<BinaryTree<T>>>; 2], // The children binary trees
-    pub actor: Option<Rc<Actor<T>>> // The actor stored in the leaf nodes
+    pub actor: Option<Rc<Actor<T>>>, // The actor stored in the leaf nodes
+    pub unbounded: Vec<Rc<Actor<T>>> // Actors with infinite extent (e.g. planes), only used by the root node
 }
 
 impl<T> BinaryTree<T>
@@ -23,16 +26,139 @@ impl<T> BinaryTree<T>
         let bounds = BoundingBox::<T>::new(Vec3::<T>::new(), Vec3::<T>::new());
         let children: [Option<Box<BinaryTree<T>>>; 2] = [None, None];
         let actor = None;
+        let unbounded = Vec::new();
         BinaryTree {
             bounds,
             children,
-            actor
+            actor,
+            unbounded
         }
     }
 
     pub fn get_bounds(&self) -> &BoundingBox<T> {
         &self.bounds
     }
+
+    fn is_empty(&self) -> bool {
+        self.actor.is_none() && self.children[0].is_none() && self.children[1].is_none()
+    }
+
+    fn get_hit_inside(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(&Rc<Actor<T>>, Hit<T>)> {
+        // The ray is already known to hit the bounds of this node
+        let mut t_max = t_max;
+        let mut result : Option<(&Rc<Actor<T>>, Hit<T>)> = None;
+        stats::count_traversal_step();
+
+        if let Some(actor) = &self.actor {
+            stats::count_primitive_test();
+            if let Some(hit) = actor.hit(ray, t_min, t_max) {
+                t_max = hit.t;
+                result = Some((actor, hit));
+            }
+        }
+
+        // Visit the children nearest first, using the distance where the ray enters their bounds
+        let mut entries = [None, None];
+        for (entry, child) in entries.iter_mut().zip(self.children.iter()) {
+            if let Some(child) = child {
+                stats::count_box_test();
+                *entry = child.get_bounds().intersect(ray, t_min, t_max);
+            }
+        }
+        let order = match entries {
+            [Some((t0, _)), Some((t1, _))] if t1 < t0 => [1, 0],
+            _ => [0, 1]
+        };
+
+        for &i in &order {
+            match (&self.children[i], entries[i]) {
+                // Children entered after the current hit can't contain a closer one
+                (Some(child), Some((t_enter, _))) if t_enter <= t_max => {
+                    if let Some((actor, hit)) = child.get_hit_inside(ray, t_min, t_max) {
+                        t_max = hit.t;
+                        result = Some((actor, hit));
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        result
+    }
+
+    fn remove_bounded(&mut self, actor: &Rc<Actor<T>>) -> bool {
+        if let Some(current_actor) = &self.actor {
+            if Rc::ptr_eq(current_actor, actor) {
+                self.actor = None;
+                return true;
+            }
+            return false;
+        }
+
+        for i in 0..2 {
+            let removed = match &mut self.children[i] {
+                Some(child) => child.remove_bounded(actor),
+                None => false
+            };
+            if !removed {
+                continue;
+            }
+            // A node with a single child is replaced by that child
+            if matches!(&self.children[i], Some(child) if child.is_empty()) {
+                if let Some(sibling) = self.children[1 - i].take() {
+                    let sibling = *sibling;
+                    self.bounds = sibling.bounds;
+                    self.children = sibling.children;
+                    self.actor = sibling.actor;
+                }
+            }
+            return true;
+        }
+        false
+    }
+
+    fn any_hit_inside(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        // The ray is already known to hit the bounds of this node
+        stats::count_traversal_step();
+        if let Some(actor) = &self.actor {
+            stats::count_primitive_test();
+            if actor.any_hit(ray, t_min, t_max) {
+                return true;
+            }
+        }
+
+        self.children.iter().flatten().any(|child| {
+            stats::count_box_test();
+            child.get_bounds().hit(ray, t_min, t_max) && child.any_hit_inside(ray, t_min, t_max)
+        })
+    }
+
+    fn collect_stats(&self, level: usize, stats: &mut TreeStats) {
+        stats.add_node(level);
+        stats.memory += std::mem::size_of::<Self>() + stats::get_vec_memory(&self.unbounded);
+        if self.children.iter().all(|child| child.is_none()) {
+            stats.add_leaf(level, self.actor.iter().count());
+        }
+        for child in self.children.iter().flatten() {
+            child.collect_stats(level + 1, stats);
+        }
+    }
+}
+
+impl<T> FlatSource<T> for BinaryTree<T>
+    where T: Float
+{
+    fn get_node_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn get_node_actors(&self) -> Vec<&Rc<Actor<T>>> {
+        self.actor.iter().collect()
+    }
+
+    fn get_node_children(&self) -> Vec<&Self> {
+        self.children.iter().flatten().map(|child| &**child).collect()
+    }
 }
 
 impl<T> Tree<T> for BinaryTree<T>
@@ -41,6 +167,13 @@ impl<T> Tree<T> for BinaryTree<T>
     fn add_actor(&mut self, actor: Rc<Actor<T>>) -> bool {
         let actor_bounds = actor.hitable.get_bounds();
 
+        // Unbounded actors would make the bounds of the whole tree infinite,
+        // keep them aside and always test them
+        if !actor_bounds.is_finite() {
+            self.unbounded.push(actor);
+            return true;
+        }
+
         // Expand the node bounds so the new actor is guaranteed to fit
         self.bounds.expand(&actor_bounds);
 
@@ -112,30 +245,184 @@ impl<T> Tree<T> for BinaryTree<T>
         }
     }
 
-    fn get_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(Rc<Actor<T>>, Hit<T>)> {
-        if !self.get_bounds().hit(ray, t_min, t_max) {
-            return None;
+    fn remove_actor(&mut self, actor: &Rc<Actor<T>>) -> bool {
+        let count = self.unbounded.len();
+        self.unbounded.retain(|current| !Rc::ptr_eq(current, actor));
+        if self.unbounded.len() < count {
+            return true;
         }
 
-        let mut t_max = t_max;
-        let mut result : Option<(Rc<Actor<T>>, Hit<T>)> = None;
+        self.remove_bounded(actor)
+    }
 
+    fn refit(&mut self) {
         if let Some(actor) = &self.actor {
-            if let Some(hit) = actor.hitable.hit(ray, t_min, t_max) {
-                t_max = hit.t;
-                result = Some((Rc::clone(actor), hit));
-            }
+            let bounds = actor.hitable.get_bounds();
+            self.bounds = BoundingBox::new(
+                Vec3::from_slice(bounds.get_p0().get_data()),
+                Vec3::from_slice(bounds.get_p1().get_data())
+            );
+            return;
         }
 
+        self.bounds = BoundingBox::new(Vec3::new(), Vec3::new());
+        let mut first = true;
         for i in 0..2 {
-            if let Some(child) = &self.children[i] {
-                if let Some((actor, hit)) = child.get_hit(ray, t_min, t_max) {
-                    t_max = hit.t;
-                    result = Some((actor, hit));
+            if let Some(child) = &mut self.children[i] {
+                child.refit();
+                if first {
+                    self.bounds = BoundingBox::new(
+                        Vec3::from_slice(child.get_bounds().get_p0().get_data()),
+                        Vec3::from_slice(child.get_bounds().get_p1().get_data())
+                    );
+                    first = false;
+                } else {
+                    self.bounds.expand(child.get_bounds());
                 }
             }
         }
+    }
 
-        result
+    fn get_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(Rc<Actor<T>>, Hit<T>)> {
+        let mut t_max = t_max;
+        let mut result : Option<(&Rc<Actor<T>>, Hit<T>)> = None;
+        stats::count_ray();
+
+        for i in 0..self.unbounded.len() {
+            stats::count_primitive_test();
+            if let Some(hit) = self.unbounded[i].hit(ray, t_min, t_max) {
+                t_max = hit.t;
+                result = Some((&self.unbounded[i], hit));
+            }
+        }
+
+        stats::count_box_test();
+        if self.get_bounds().hit(ray, t_min, t_max) {
+            if let Some(found) = self.get_hit_inside(ray, t_min, t_max) {
+                result = Some(found);
+            }
+        }
+
+        result.map(|(actor, hit)| (Rc::clone(actor), hit))
+    }
+
+    fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        stats::count_ray();
+        if self.unbounded.iter().any(|actor| {
+            stats::count_primitive_test();
+            actor.any_hit(ray, t_min, t_max)
+        }) {
+            return true;
+        }
+
+        stats::count_box_test();
+        self.get_bounds().hit(ray, t_min, t_max) && self.any_hit_inside(ray, t_min, t_max)
+    }
+
+    fn flatten(&self) -> FlatTree<T> {
+        FlatTree::from_nodes(self, &self.unbounded)
+    }
+
+    fn get_stats(&self) -> TreeStats {
+        let mut stats = TreeStats::new();
+        stats.unbounded = self.unbounded.len();
+        self.collect_stats(0, &mut stats);
+        stats
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::linear::LinearTree;
+    use crate::tree::fixtures::{make_actor, make_actors, check_same_hits};
+
+    #[test]
+    fn same_hits() {
+        let actors = make_actors();
+        let mut tree = BinaryTree::new();
+        let mut reference = LinearTree::new();
+        for actor in &actors {
+            assert!(tree.add_actor(Rc::clone(actor)));
+            reference.add_actor(Rc::clone(actor));
+        }
+        check_same_hits(&tree, &reference);
+
+        for actor in actors.iter().step_by(3) {
+            assert!(tree.remove_actor(actor));
+            assert!(reference.remove_actor(actor));
+        }
+        tree.refit();
+        check_same_hits(&tree, &reference);
+    }
+
+    #[test]
+    fn remove_and_refit() {
+        let mut tree = BinaryTree::new();
+        let actors: Vec<Rc<Actor<f64>>> = (0..5).map(|i| make_actor([4.0 * i as f64, 0.0, 0.0], 1.0)).collect();
+        for actor in &actors {
+            tree.add_actor(Rc::clone(actor));
+        }
+
+        assert!(tree.remove_actor(&actors[4]));
+        assert!(!tree.remove_actor(&actors[4]));
+        tree.refit();
+        assert_eq!(tree.get_bounds().get_p0().get_data(), [-1.0, -1.0, -1.0]);
+        assert_eq!(tree.get_bounds().get_p1().get_data(), [13.0, 1.0, 1.0]);
+
+        let ray = Ray::from_array([16.0, 0.0, 10.0], [0.0, 0.0, -1.0]);
+        assert!(tree.get_hit(&ray, 0.0, 100.0).is_none());
+        assert!(!tree.any_hit(&ray, 0.0, 100.0));
+        let ray = Ray::from_array([8.0, 0.0, 10.0], [0.0, 0.0, -1.0]);
+        match tree.get_hit(&ray, 0.0, 100.0) {
+            Some((actor, _)) => {
+                assert!(Rc::ptr_eq(&actor, &actors[2]));
+            },
+            None => {
+                assert!(false);
+            }
+        }
+        assert!(tree.any_hit(&ray, 0.0, 100.0));
+        assert!(!tree.any_hit(&ray, 0.0, 8.0));
+
+        for actor in &actors[0..4] {
+            assert!(tree.remove_actor(actor));
+        }
+        assert!(tree.is_empty());
+        assert!(tree.get_hit(&ray, 0.0, 100.0).is_none());
+    }
+
+    #[test]
+    fn stats() {
+        let mut tree = BinaryTree::new();
+        for i in 0..4 {
+            tree.add_actor(make_actor([4.0 * i as f64, 0.0, 0.0], 1.0));
+        }
+
+        // Every actor in its own leaf
+        let stats = tree.get_stats();
+        assert_eq!(stats.node_count, 7);
+        assert_eq!(stats.leaf_occupancy, [0, 4]);
+        assert!(stats.depth >= 3);
+        assert!(stats.memory >= 7 * std::mem::size_of::<BinaryTree<f64>>());
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    fn counters() {
+        let mut tree = BinaryTree::new();
+        for i in 0..4 {
+            tree.add_actor(make_actor([4.0 * i as f64, 0.0, 0.0], 1.0));
+        }
+        let depth = tree.get_stats().depth;
+
+        stats::reset_counters();
+        let ray = Ray::from_array([4.0, 0.0, 10.0], [0.0, 0.0, -1.0]);
+        assert!(tree.get_hit(&ray, 0.0, 100.0).is_some());
+        let counters = stats::get_counters();
+        assert_eq!(counters.rays, 1);
+        assert!(counters.primitive_tests < 4);
+        assert!(counters.traversal_steps >= depth);
+    }
+}
+
@@ -0,0 +1,513 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use crate::float::Float;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::boundingbox::BoundingBox;
+use crate::actor::Actor;
+use crate::tree::Tree;
+use crate::tree::stats::{self, TreeStats};
+
+// A node of the flattened tree. Its children and its actors are contiguous
+// ranges of the node and actor arrays of the tree.
+#[derive(Clone)]
+struct FlatNode<T>
+    where T: Float
+{
+    bounds: BoundingBox<T>,
+    first_child: usize,
+    child_count: usize,
+    first_actor: usize,
+    actor_count: usize,
+    split: Option<(usize, T)> // The axis and position of the split plane of kd-tree nodes, their children are below and above it
+}
+
+// A node to visit, with the range of the ray inside it
+type NodeRange<T> = (usize, T, T);
+
+// A ray with the inverse of its direction, computed once for all the boxes it is tested against
+struct TraversalRay<'a, T>
+    where T: Float
+{
+    ray: &'a Ray<T>,
+    inv_direction: [T; 3]
+}
+
+// The nodes of a tree that can be compacted into a flat tree
+pub trait FlatSource<T>
+    where T: Float
+{
+    fn get_node_bounds(&self) -> &BoundingBox<T>;
+    fn get_node_actors(&self) -> Vec<&Rc<Actor<T>>>;
+    fn get_node_children(&self) -> Vec<&Self>;
+
+    // Nodes split by a plane have two children, below and above it.
+    // The traversal then goes by the plane instead of testing the bounds of the children.
+    fn get_node_split(&self) -> Option<(usize, T)> {
+        None
+    }
+}
+
+// A compacted copy of another tree, stored in flat arrays in breadth first order.
+// The traversal uses an explicit stack and only clones the actor of the closest hit.
+// Actors can be removed, but new bounded actors can't be added.
+#[derive(Clone)]
+pub struct FlatTree<T>
+    where T: Float
+{
+    nodes: Vec<FlatNode<T>>,
+    actors: Vec<Rc<Actor<T>>>,
+    unbounded: Vec<Rc<Actor<T>>>, // Actors with infinite extent (e.g. planes)
+    stack: RefCell<Vec<NodeRange<T>>> // Kept between traversals, so that rays don't allocate
+}
+
+impl<T> FlatTree<T>
+    where T: Float
+{
+    pub fn new() -> Self {
+        FlatTree {
+            nodes: Vec::new(),
+            actors: Vec::new(),
+            unbounded: Vec::new(),
+            stack: RefCell::new(Vec::new())
+        }
+    }
+
+    pub fn from_nodes<N>(root: &N, unbounded: &[Rc<Actor<T>>]) -> Self
+        where N: FlatSource<T>
+    {
+        let mut tree = FlatTree::new();
+        tree.unbounded = unbounded.to_vec();
+        tree.nodes.push(FlatNode::new(root.get_node_bounds().clone()));
+
+        // Breadth first, so that the children of each node are next to each other
+        let mut queue = VecDeque::new();
+        queue.push_back((root, 0));
+        while let Some((node, index)) = queue.pop_front() {
+            let first_actor = tree.actors.len();
+            for actor in node.get_node_actors() {
+                tree.actors.push(Rc::clone(actor));
+            }
+            let first_child = tree.nodes.len();
+            for child in node.get_node_children() {
+                queue.push_back((child, tree.nodes.len()));
+                tree.nodes.push(FlatNode::new(child.get_node_bounds().clone()));
+            }
+
+            let actor_count = tree.actors.len() - first_actor;
+            let child_count = tree.nodes.len() - first_child;
+            let flat_node = &mut tree.nodes[index];
+            flat_node.first_actor = first_actor;
+            flat_node.actor_count = actor_count;
+            flat_node.first_child = first_child;
+            flat_node.child_count = child_count;
+            flat_node.split = node.get_node_split();
+        }
+
+        // Octree cells are usually larger than what they contain
+        tree.refit();
+        tree
+    }
+
+    pub fn from_actors(actors: &[Rc<Actor<T>>]) -> Self {
+        // A single node holding all the bounded actors
+        let mut tree = FlatTree::new();
+        let mut bounds: Option<BoundingBox<T>> = None;
+        for actor in actors {
+            let actor_bounds = actor.hitable.get_bounds();
+            if !actor_bounds.is_finite() {
+                tree.unbounded.push(Rc::clone(actor));
+                continue;
+            }
+            match &mut bounds {
+                Some(bounds) => {
+                    bounds.expand(actor_bounds);
+                },
+                None => {
+                    bounds = Some(actor_bounds.clone());
+                }
+            }
+            tree.actors.push(Rc::clone(actor));
+        }
+
+        if let Some(bounds) = bounds {
+            let mut root = FlatNode::new(bounds);
+            root.actor_count = tree.actors.len();
+            tree.nodes.push(root);
+        }
+        tree
+    }
+
+    pub fn get_node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn get_actor_count(&self) -> usize {
+        self.actors.len() + self.unbounded.len()
+    }
+
+    // The children of a node split by a plane that the ray goes through, nearest first,
+    // with the range of the ray inside them
+    #[inline(always)]
+    fn get_split_children(&self, node: &FlatNode<T>, split: (usize, T), ray: &TraversalRay<T>, range: (T, T)) -> (Option<NodeRange<T>>, Option<NodeRange<T>>) {
+        let (axis, position) = split;
+        let (t_near, t_far) = range;
+        let origin = ray.ray.get_origin().get_data()[axis];
+        let direction = ray.ray.get_direction().get_data()[axis];
+        let t_plane = if direction != T::zero() {
+            (position - origin) * ray.inv_direction[axis]
+        } else {
+            T::infinity()
+        };
+        let first = node.first_child;
+        let (near, far) = if origin < position || (origin == position && direction <= T::zero()) {
+            (first, first + 1)
+        } else {
+            (first + 1, first)
+        };
+
+        // A ray lying in the plane goes through both sides all along,
+        // e.g. along the edge of actors starting or ending on the plane
+        let (near, far) = if direction == T::zero() && origin == position {
+            ((near, t_near, t_far), Some((far, t_near, t_far)))
+        } else if t_plane > t_far || t_plane <= T::zero() {
+            // The ray only goes through one side of the split plane
+            ((near, t_near, t_far), None)
+        } else if t_plane < t_near {
+            ((far, t_near, t_far), None)
+        } else {
+            ((near, t_near, t_plane), Some((far, t_plane, t_far)))
+        };
+
+        // Empty leaves, e.g. the empty space cut off by the surface area heuristic, aren't visited
+        let far = far.filter(|far| !self.nodes[far.0].is_empty_leaf());
+        if self.nodes[near.0].is_empty_leaf() {
+            (far, None)
+        } else {
+            (Some(near), far)
+        }
+    }
+
+    // Pushes the children of the node that the ray goes through, with the range of the ray inside them.
+    // The nearest children are pushed last, so that they are visited first,
+    // except the nearest one which is returned to be visited right away.
+    #[inline(always)]
+    fn push_children(&self, node: &FlatNode<T>, ray: &TraversalRay<T>, t_min: T, t_max: T, range: (T, T), stack: &mut Vec<NodeRange<T>>) -> Option<NodeRange<T>> {
+        if let Some(split) = node.split {
+            let (near, far) = self.get_split_children(node, split, ray, range);
+            if let Some(far) = far {
+                stack.push(far);
+            }
+            return near;
+        }
+
+        let origin = ray.ray.get_origin().get_data();
+        let first = node.first_child;
+        let children = &self.nodes[first..first + node.child_count];
+
+        // The children of binary tree nodes, without the general sort
+        if let [left, right] = children {
+            stats::count_box_test();
+            stats::count_box_test();
+            let left = left.bounds.intersect_inverse(origin, &ray.inv_direction, t_min, t_max).map(|(t_enter, t_exit)| (first, t_enter, t_exit));
+            let right = right.bounds.intersect_inverse(origin, &ray.inv_direction, t_min, t_max).map(|(t_enter, t_exit)| (first + 1, t_enter, t_exit));
+            return match (left, right) {
+                (Some(left), Some(right)) => {
+                    let (near, far) = if right.1 < left.1 { (right, left) } else { (left, right) };
+                    stack.push(far);
+                    Some(near)
+                },
+                (left, right) => left.or(right)
+            };
+        }
+
+        let mut nearest: Option<NodeRange<T>> = None;
+        let start = stack.len();
+        for (i, child) in children.iter().enumerate() {
+            stats::count_box_test();
+            if let Some((t_enter, t_exit)) = child.bounds.intersect_inverse(origin, &ray.inv_direction, t_min, t_max) {
+                let mut entry = (node.first_child + i, t_enter, t_exit);
+                match &mut nearest {
+                    Some(current) => {
+                        if t_enter < current.1 {
+                            std::mem::swap(current, &mut entry);
+                        }
+                    },
+                    None => {
+                        nearest = Some(entry);
+                        continue;
+                    }
+                }
+                stack.push(entry);
+                let mut j = stack.len() - 1;
+                while j > start && stack[j - 1].1 < entry.1 {
+                    stack.swap(j - 1, j);
+                    j -= 1;
+                }
+            }
+        }
+        nearest
+    }
+}
+
+impl<'a, T> TraversalRay<'a, T>
+    where T: Float
+{
+    fn new(ray: &'a Ray<T>) -> Self {
+        let direction = ray.get_direction().get_data();
+        let inv_direction = [T::one() / direction[0], T::one() / direction[1], T::one() / direction[2]];
+        TraversalRay {ray, inv_direction}
+    }
+}
+
+impl<T> FlatNode<T>
+    where T: Float
+{
+    fn is_empty_leaf(&self) -> bool {
+        self.actor_count == 0 && self.child_count == 0
+    }
+
+    fn new(bounds: BoundingBox<T>) -> Self {
+        FlatNode {
+            bounds,
+            first_child: 0,
+            child_count: 0,
+            first_actor: 0,
+            actor_count: 0,
+            split: None
+        }
+    }
+}
+
+impl<T> Tree<T> for FlatTree<T>
+    where T: Float
+{
+    fn add_actor(&mut self, actor: Rc<Actor<T>>) -> bool {
+        // Unbounded actors are kept aside, the rest needs the tree to be rebuilt
+        if !actor.hitable.get_bounds().is_finite() {
+            self.unbounded.push(actor);
+            return true;
+        }
+        false
+    }
+
+    fn remove_actor(&mut self, actor: &Rc<Actor<T>>) -> bool {
+        let count = self.unbounded.len();
+        self.unbounded.retain(|current| !Rc::ptr_eq(current, actor));
+        if self.unbounded.len() < count {
+            return true;
+        }
+
+        // Actors straddling the split planes of a kd-tree are in several nodes
+        let mut removed = false;
+        while let Some(index) = self.actors.iter().position(|current| Rc::ptr_eq(current, actor)) {
+            self.actors.remove(index);
+            // Shift the actor ranges that come after the removed actor
+            for node in self.nodes.iter_mut() {
+                if node.first_actor > index {
+                    node.first_actor -= 1;
+                } else if index < node.first_actor + node.actor_count {
+                    node.actor_count -= 1;
+                }
+            }
+            removed = true;
+        }
+        removed
+    }
+
+    fn refit(&mut self) {
+        // The children come after their parent, so they are refitted first
+        for i in (0..self.nodes.len()).rev() {
+            let node = &self.nodes[i];
+            let mut bounds: Option<BoundingBox<T>> = None;
+            let actors = &self.actors[node.first_actor..node.first_actor + node.actor_count];
+            let actor_bounds = actors.iter().map(|actor| actor.hitable.get_bounds());
+            let children = &self.nodes[node.first_child..node.first_child + node.child_count];
+            let child_bounds = children.iter().map(|child| &child.bounds);
+            for other in actor_bounds.chain(child_bounds) {
+                match &mut bounds {
+                    Some(bounds) => {
+                        bounds.expand(other);
+                    },
+                    None => {
+                        bounds = Some(other.clone());
+                    }
+                }
+            }
+            // Empty nodes keep their bounds
+            if let Some(bounds) = bounds {
+                self.nodes[i].bounds = bounds;
+            }
+        }
+    }
+
+    fn get_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(Rc<Actor<T>>, Hit<T>)> {
+        let mut t_max = t_max;
+        let mut result : Option<(&Rc<Actor<T>>, Hit<T>)> = None;
+        stats::count_ray();
+
+        for actor in self.unbounded.iter() {
+            stats::count_primitive_test();
+            if let Some(hit) = actor.hit(ray, t_min, t_max) {
+                t_max = hit.t;
+                result = Some((actor, hit));
+            }
+        }
+
+        let root = match self.nodes.first() {
+            Some(root) => root,
+            None => return result.map(|(actor, hit)| (Rc::clone(actor), hit))
+        };
+
+        // The next node to visit, and the nodes left for later, with the range of the ray inside them
+        let traversal_ray = TraversalRay::new(ray);
+        let mut stack = self.stack.take();
+        stack.clear();
+        stats::count_box_test();
+        let mut next = root.bounds.intersect(ray, t_min, t_max).map(|(t_near, t_far)| (0, t_near, t_far));
+
+        'nodes: while let Some(mut current) = next.take().or_else(|| stack.pop()) {
+            // The node was entered after the current closest hit
+            if current.1 > t_max {
+                continue;
+            }
+            // Nodes without actors (e.g. split nodes) are descended directly to their nearest child
+            let mut node = &self.nodes[current.0];
+            while node.actor_count == 0 && node.child_count > 0 {
+                stats::count_traversal_step();
+                match self.push_children(node, &traversal_ray, t_min, t_max, (current.1, current.2), &mut stack) {
+                    Some(nearest) => {
+                        current = nearest;
+                        node = &self.nodes[current.0];
+                    },
+                    None => continue 'nodes
+                }
+            }
+            let (_, t_near, t_far) = current;
+            stats::count_traversal_step();
+
+            for actor in &self.actors[node.first_actor..node.first_actor + node.actor_count] {
+                stats::count_primitive_test();
+                if let Some(hit) = actor.hit(ray, t_min, t_max) {
+                    t_max = hit.t;
+                    result = Some((actor, hit));
+                }
+            }
+
+            next = self.push_children(node, &traversal_ray, t_min, t_max, (t_near, t_far), &mut stack);
+        }
+        self.stack.replace(stack);
+
+        result.map(|(actor, hit)| (Rc::clone(actor), hit))
+    }
+
+    fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        stats::count_ray();
+        let any_hit = |actor: &Rc<Actor<T>>| {
+            stats::count_primitive_test();
+            actor.any_hit(ray, t_min, t_max)
+        };
+        if self.unbounded.iter().any(any_hit) {
+            return true;
+        }
+
+        let traversal_ray = TraversalRay::new(ray);
+        let mut stack = Vec::new();
+        let mut next = None;
+        if let Some(root) = self.nodes.first() {
+            stats::count_box_test();
+            next = root.bounds.intersect(ray, t_min, t_max).map(|(t_near, t_far)| (0, t_near, t_far));
+        }
+        while let Some((index, t_near, t_far)) = next.take().or_else(|| stack.pop()) {
+            let node = &self.nodes[index];
+            stats::count_traversal_step();
+            let actors = &self.actors[node.first_actor..node.first_actor + node.actor_count];
+            if actors.iter().any(any_hit) {
+                return true;
+            }
+            next = self.push_children(node, &traversal_ray, t_min, t_max, (t_near, t_far), &mut stack);
+        }
+        false
+    }
+
+    fn flatten(&self) -> FlatTree<T> {
+        self.clone()
+    }
+
+    fn get_stats(&self) -> TreeStats {
+        let mut stats = TreeStats::new();
+        stats.unbounded = self.unbounded.len();
+        stats.memory = std::mem::size_of::<Self>() + stats::get_vec_memory(&self.nodes)
+            + stats::get_vec_memory(&self.actors) + stats::get_vec_memory(&self.unbounded);
+
+        // The children come after their parent, so their level is known when they are reached
+        let mut levels = vec![0; self.nodes.len()];
+        for (index, node) in self.nodes.iter().enumerate() {
+            stats.add_node(levels[index]);
+            if node.actor_count > 0 || node.child_count == 0 {
+                stats.add_leaf(levels[index], node.actor_count);
+            }
+            for child in node.first_child..node.first_child + node.child_count {
+                levels[child] = levels[index] + 1;
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Vec3;
+    use crate::tree::binary::BinaryTree;
+    use crate::tree::oct::Octree;
+    use crate::tree::fixtures::{make_actor, make_actors, check_same_hits};
+
+    #[test]
+    fn flatten() {
+        let actors = make_actors();
+
+        let mut binary = BinaryTree::new();
+        let bounds = BoundingBox::new(Vec3::from_array([-8.0; 3]), Vec3::from_array([16.0; 3]));
+        let mut oct = Octree::new(bounds);
+        for actor in &actors {
+            binary.add_actor(Rc::clone(actor));
+            oct.add_actor(Rc::clone(actor));
+        }
+
+        let flat = binary.flatten();
+        // Each leaf holds one actor, and each internal node two children
+        assert_eq!(flat.get_node_count(), 2 * actors.len() - 1);
+        assert_eq!(flat.get_actor_count(), actors.len());
+        check_same_hits(&flat, &binary);
+
+        let flat = oct.flatten();
+        assert_eq!(flat.get_actor_count(), actors.len());
+        check_same_hits(&flat, &oct);
+    }
+
+    #[test]
+    fn remove_and_refit() {
+        let actors = make_actors();
+        let mut binary = BinaryTree::new();
+        for actor in &actors {
+            binary.add_actor(Rc::clone(actor));
+        }
+        let mut flat = binary.flatten();
+
+        for actor in actors.iter().step_by(3) {
+            assert!(binary.remove_actor(actor));
+            assert!(flat.remove_actor(actor));
+            assert!(!flat.remove_actor(actor));
+        }
+        binary.refit();
+        flat.refit();
+        assert_eq!(flat.get_actor_count(), 34);
+        check_same_hits(&flat, &binary);
+
+        // Only unbounded actors can be added without rebuilding the tree
+        assert!(!flat.add_actor(make_actor([0.0; 3], 1.0)));
+    }
+}
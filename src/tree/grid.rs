@@ -0,0 +1,508 @@
+use std::rc::Rc;
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::boundingbox::BoundingBox;
+use crate::actor::Actor;
+use crate::tree::Tree;
+use crate::tree::flat::{FlatTree, FlatSource};
+use crate::tree::stats::{self, TreeStats};
+
+// The grid has about this many cells per actor
+const CELLS_PER_ACTOR: f64 = 2.0;
+const MAX_RESOLUTION: usize = 128;
+// Actors larger than this many times the median actor size are kept out of the cells
+const LARGE_ACTOR_SIZE: f64 = 16.0;
+
+// A uniform grid of cells over the bounds of the actors, each cell refers to the actors overlapping it.
+// Actors much larger than the median one are kept out of the cells, and tested for every ray.
+// Actors added after the grid is built are placed in the existing cells if they fit,
+// the grid is built again when they don't, when their number doubles or when it is rebuilt.
+pub struct GridTree<T>
+    where T: Float
+{
+    bounds: BoundingBox<T>,
+    resolution: [usize; 3],
+    cell_size: [T; 3],
+    cells: Vec<Vec<Rc<Actor<T>>>>,
+    large: Vec<Rc<Actor<T>>>, // Actors that would make the cells too coarse, always tested
+    size_limit: T, // The size above which actors are kept out of the cells
+    actors: Vec<Rc<Actor<T>>>, // All the bounded actors, to build the grid again
+    built_count: usize, // The number of actors when the grid was last built
+    modified: bool, // Whether actors were added or removed since the grid was last built
+    unbounded: Vec<Rc<Actor<T>>> // Actors with infinite extent (e.g. planes)
+}
+
+// A block of cells, the grid is flattened into a hierarchy of blocks
+struct GridBlock<'a, T>
+    where T: Float
+{
+    bounds: BoundingBox<T>,
+    actors: Vec<&'a Rc<Actor<T>>>,
+    children: Vec<GridBlock<'a, T>>
+}
+
+impl<T> GridTree<T>
+    where T: Float
+{
+    pub fn new() -> Self {
+        GridTree {
+            bounds: BoundingBox::<T>::new(Vec3::<T>::new(), Vec3::<T>::new()),
+            resolution: [1, 1, 1],
+            cell_size: [T::zero(); 3],
+            cells: vec![Vec::new()],
+            large: Vec::new(),
+            size_limit: T::infinity(),
+            actors: Vec::new(),
+            built_count: 0,
+            modified: false,
+            unbounded: Vec::new()
+        }
+    }
+
+    pub fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    pub fn get_resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    fn build(&mut self) {
+        // A few very large actors (e.g. a ground) would stretch the grid over mostly empty cells
+        let mut sizes: Vec<T> = self.actors.iter().map(|actor| GridTree::get_size(actor)).collect();
+        sizes.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        self.size_limit = match sizes.get(sizes.len() / 2) {
+            Some(&median) if median > T::zero() => median * T::from(LARGE_ACTOR_SIZE).unwrap(),
+            _ => T::infinity()
+        };
+        let size_limit = self.size_limit;
+        let (large, actors): (Vec<_>, Vec<_>) = self.actors.iter()
+            .map(Rc::clone)
+            .partition(|actor| GridTree::get_size(actor) > size_limit);
+        self.large = large;
+
+        self.bounds = BoundingBox::<T>::new(Vec3::<T>::new(), Vec3::<T>::new());
+        if let Some((first, rest)) = actors.split_first() {
+            self.bounds = first.hitable.get_bounds().clone();
+            for actor in rest {
+                self.bounds.expand(actor.hitable.get_bounds());
+            }
+        }
+
+        self.resolution = GridTree::get_resolution_for(&self.bounds, actors.len());
+        for axis in 0..3 {
+            let length = self.bounds.get_axis_length(axis);
+            self.cell_size[axis] = length / T::from(self.resolution[axis]).unwrap();
+        }
+        self.cells = vec![Vec::new(); self.resolution[0] * self.resolution[1] * self.resolution[2]];
+        for actor in actors {
+            self.insert(actor);
+        }
+
+        self.built_count = self.actors.len();
+        self.modified = false;
+    }
+
+    fn get_size(actor: &Actor<T>) -> T {
+        let bounds = actor.hitable.get_bounds();
+        let mut size = T::zero();
+        for axis in 0..3 {
+            if bounds.get_axis_length(axis) > size {
+                size = bounds.get_axis_length(axis);
+            }
+        }
+        size
+    }
+
+    fn get_resolution_for(bounds: &BoundingBox<T>, count: usize) -> [usize; 3] {
+        // Cells as close to cubes as the bounds allow, sized from the density of the actors.
+        // Thin axes are treated as a fraction of the longest one, so that flat scenes still have a volume.
+        let mut max_length = T::zero();
+        for axis in 0..3 {
+            if bounds.get_axis_length(axis) > max_length {
+                max_length = bounds.get_axis_length(axis);
+            }
+        }
+        if count == 0 || max_length <= T::zero() {
+            return [1, 1, 1];
+        }
+
+        let min_length = max_length / T::from(MAX_RESOLUTION).unwrap();
+        let mut volume = T::one();
+        for axis in 0..3 {
+            let length = bounds.get_axis_length(axis);
+            volume = volume * if length > min_length { length } else { min_length };
+        }
+        let density = T::from(count).unwrap() / volume;
+        let cell_size = (T::from(CELLS_PER_ACTOR).unwrap() * density).cbrt().recip();
+
+        let mut resolution = [1, 1, 1];
+        for (axis, cells) in resolution.iter_mut().enumerate() {
+            let count = (bounds.get_axis_length(axis) / cell_size).round();
+            *cells = T::to_usize(&count).unwrap_or(1).clamp(1, MAX_RESOLUTION);
+        }
+        resolution
+    }
+
+    fn get_cell_index(&self, cell: [usize; 3]) -> usize {
+        (cell[0] * self.resolution[1] + cell[1]) * self.resolution[2] + cell[2]
+    }
+
+    fn get_cell_coordinate(&self, position: T, axis: usize) -> usize {
+        // The cell containing the position along the axis, clamped to the grid
+        let (min, _) = self.bounds.get_axis_bounds(axis);
+        let last = self.resolution[axis] - 1;
+        if last == 0 {
+            return 0;
+        }
+        let position = T::to_isize(&((position - min) / self.cell_size[axis]).floor()).unwrap_or(0);
+        if position < 0 { 0 } else if position as usize > last { last } else { position as usize }
+    }
+
+    fn get_cell_bounds(&self, cell: [usize; 3], size: [usize; 3]) -> BoundingBox<T> {
+        // The bounds of a block of cells, from its first cell and its size
+        let mut p0 = Vec3::from_slice(self.bounds.get_p0().get_data());
+        let mut p1 = Vec3::from_slice(self.bounds.get_p0().get_data());
+        for axis in 0..3 {
+            p0.get_data_mut()[axis] = p0.get_data()[axis] + self.cell_size[axis] * T::from(cell[axis]).unwrap();
+            p1.get_data_mut()[axis] = p0.get_data()[axis] + self.cell_size[axis] * T::from(size[axis]).unwrap();
+        }
+        BoundingBox::new(p0, p1)
+    }
+
+    fn insert(&mut self, actor: Rc<Actor<T>>) {
+        // The actor goes in every cell its bounds overlap
+        let mut first = [0; 3];
+        let mut last = [0; 3];
+        for axis in 0..3 {
+            let (min, max) = actor.hitable.get_bounds().get_axis_bounds(axis);
+            first[axis] = self.get_cell_coordinate(min, axis);
+            last[axis] = self.get_cell_coordinate(max, axis);
+        }
+        for i in first[0]..=last[0] {
+            for j in first[1]..=last[1] {
+                for k in first[2]..=last[2] {
+                    let index = self.get_cell_index([i, j, k]);
+                    self.cells[index].push(Rc::clone(&actor));
+                }
+            }
+        }
+    }
+
+    fn walk<'a, F>(&'a self, ray: &Ray<T>, t_min: T, t_max: T, mut visit: F)
+        where F: FnMut(&'a [Rc<Actor<T>>], T) -> bool
+    {
+        // Visits the cells along the ray in order with a 3D DDA (Amanatides and Woo),
+        // with the distance where the ray leaves each cell, until visit returns true
+        stats::count_box_test();
+        let (t_enter, t_exit) = match self.bounds.intersect(ray, t_min, t_max) {
+            Some(range) => range,
+            None => return
+        };
+
+        let origin = ray.get_origin().get_data();
+        let direction = ray.get_direction().get_data();
+        let start = ray.get_point(t_enter);
+
+        let mut cell = [0; 3];
+        let mut step = [0isize; 3];
+        let mut t_next = [T::infinity(); 3];
+        let mut t_delta = [T::infinity(); 3];
+        for axis in 0..3 {
+            cell[axis] = self.get_cell_coordinate(start.get_data()[axis], axis);
+            let (min, _) = self.bounds.get_axis_bounds(axis);
+            if direction[axis] > T::zero() {
+                step[axis] = 1;
+                let boundary = min + self.cell_size[axis] * T::from(cell[axis] + 1).unwrap();
+                t_next[axis] = (boundary - origin[axis]) / direction[axis];
+                t_delta[axis] = self.cell_size[axis] / direction[axis];
+            } else if direction[axis] < T::zero() {
+                step[axis] = -1;
+                let boundary = min + self.cell_size[axis] * T::from(cell[axis]).unwrap();
+                t_next[axis] = (boundary - origin[axis]) / direction[axis];
+                t_delta[axis] = - self.cell_size[axis] / direction[axis];
+            }
+        }
+
+        loop {
+            let mut axis = if t_next[0] < t_next[1] { 0 } else { 1 };
+            axis = if t_next[2] < t_next[axis] { 2 } else { axis };
+            let t_leave = if t_next[axis] < t_exit { t_next[axis] } else { t_exit };
+
+            stats::count_traversal_step();
+            if visit(&self.cells[self.get_cell_index(cell)], t_leave) {
+                return;
+            }
+
+            if t_leave >= t_exit {
+                return;
+            }
+            if (step[axis] < 0 && cell[axis] == 0) || (step[axis] > 0 && cell[axis] == self.resolution[axis] - 1) {
+                return;
+            }
+            cell[axis] = (cell[axis] as isize + step[axis]) as usize;
+            t_next[axis] = t_next[axis] + t_delta[axis];
+        }
+    }
+
+    fn get_block(&self, first: [usize; 3], size: [usize; 3]) -> Option<GridBlock<'_, T>> {
+        // Blocks are halved along their longest side down to single cells, empty blocks are left out
+        if size == [1, 1, 1] {
+            let cell = &self.cells[self.get_cell_index(first)];
+            if cell.is_empty() {
+                return None;
+            }
+            return Some(GridBlock {
+                bounds: self.get_cell_bounds(first, size),
+                actors: cell.iter().collect(),
+                children: Vec::new()
+            });
+        }
+
+        let mut axis = if size[0] > size[1] { 0 } else { 1 };
+        axis = if size[2] > size[axis] { 2 } else { axis };
+        let mut below_size = size;
+        below_size[axis] = size[axis] / 2;
+        let mut above_first = first;
+        above_first[axis] = first[axis] + below_size[axis];
+        let mut above_size = size;
+        above_size[axis] = size[axis] - below_size[axis];
+
+        let mut children: Vec<GridBlock<T>> = self.get_block(first, below_size).into_iter()
+            .chain(self.get_block(above_first, above_size))
+            .collect();
+        match children.len() {
+            0 => None,
+            1 => children.pop(),
+            _ => Some(GridBlock {
+                bounds: self.get_cell_bounds(first, size),
+                actors: Vec::new(),
+                children
+            })
+        }
+    }
+}
+
+impl<'a, T> FlatSource<T> for GridBlock<'a, T>
+    where T: Float
+{
+    fn get_node_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn get_node_actors(&self) -> Vec<&Rc<Actor<T>>> {
+        self.actors.clone()
+    }
+
+    fn get_node_children(&self) -> Vec<&Self> {
+        self.children.iter().collect()
+    }
+}
+
+impl<T> Tree<T> for GridTree<T>
+    where T: Float
+{
+    fn add_actor(&mut self, actor: Rc<Actor<T>>) -> bool {
+        // Unbounded actors would cover every cell, keep them aside and always test them
+        if !actor.hitable.get_bounds().is_finite() {
+            self.unbounded.push(actor);
+            return true;
+        }
+
+        self.actors.push(Rc::clone(&actor));
+        self.modified = true;
+        if self.actors.len() >= 2 * self.built_count {
+            self.build();
+        } else if GridTree::get_size(&actor) > self.size_limit {
+            self.large.push(actor);
+        } else if self.bounds.contains(actor.hitable.get_bounds()) {
+            self.insert(actor);
+        } else {
+            self.build();
+        }
+        true
+    }
+
+    fn remove_actor(&mut self, actor: &Rc<Actor<T>>) -> bool {
+        let count = self.unbounded.len();
+        self.unbounded.retain(|current| !Rc::ptr_eq(current, actor));
+        if self.unbounded.len() < count {
+            return true;
+        }
+
+        let count = self.actors.len();
+        self.actors.retain(|current| !Rc::ptr_eq(current, actor));
+        if self.actors.len() == count {
+            return false;
+        }
+        self.large.retain(|current| !Rc::ptr_eq(current, actor));
+        for cell in self.cells.iter_mut() {
+            cell.retain(|current| !Rc::ptr_eq(current, actor));
+        }
+        self.modified = true;
+        true
+    }
+
+    fn refit(&mut self) {
+        // The cells are tied to the bounds of the grid, and the removed actors are already out of them
+    }
+
+    fn rebuild(&mut self) {
+        // The resolution only fits the actors the grid was built with
+        if self.modified {
+            self.build();
+        }
+    }
+
+    fn get_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(Rc<Actor<T>>, Hit<T>)> {
+        let mut t_max = t_max;
+        let mut result : Option<(&Rc<Actor<T>>, Hit<T>)> = None;
+        stats::count_ray();
+
+        for actor in self.unbounded.iter().chain(self.large.iter()) {
+            stats::count_primitive_test();
+            if let Some(hit) = actor.hit(ray, t_min, t_max) {
+                t_max = hit.t;
+                result = Some((actor, hit));
+            }
+        }
+
+        let range = t_max;
+        self.walk(ray, t_min, range, |actors, t_leave| {
+            for actor in actors {
+                stats::count_primitive_test();
+                if let Some(hit) = actor.hit(ray, t_min, t_max) {
+                    t_max = hit.t;
+                    result = Some((actor, hit));
+                }
+            }
+            // A hit inside the current cell is closer than anything in the next ones,
+            // a hit further away may be on an actor that also overlaps them
+            t_max <= t_leave
+        });
+
+        result.map(|(actor, hit)| (Rc::clone(actor), hit))
+    }
+
+    fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        stats::count_ray();
+        let any_hit = |actor: &Rc<Actor<T>>| {
+            stats::count_primitive_test();
+            actor.any_hit(ray, t_min, t_max)
+        };
+        if self.unbounded.iter().chain(self.large.iter()).any(any_hit) {
+            return true;
+        }
+
+        let mut found = false;
+        self.walk(ray, t_min, t_max, |actors, _| {
+            found = actors.iter().any(any_hit);
+            found
+        });
+        found
+    }
+
+    fn flatten(&self) -> FlatTree<T> {
+        // The large actors are always tested, as the unbounded ones
+        let unbounded = [self.unbounded.as_slice(), self.large.as_slice()].concat();
+        match self.get_block([0; 3], self.resolution) {
+            Some(root) => FlatTree::from_nodes(&root, &unbounded),
+            None => FlatTree::from_actors(&unbounded)
+        }
+    }
+
+    fn get_stats(&self) -> TreeStats {
+        // The cells are the leaves below the grid
+        let mut stats = TreeStats::new();
+        stats.unbounded = self.unbounded.len() + self.large.len();
+        stats.add_node(0);
+        stats.memory = std::mem::size_of::<Self>() + stats::get_vec_memory(&self.cells)
+            + stats::get_vec_memory(&self.large) + stats::get_vec_memory(&self.actors) + stats::get_vec_memory(&self.unbounded);
+        for cell in &self.cells {
+            stats.add_node(1);
+            stats.add_leaf(1, cell.len());
+            stats.memory += stats::get_vec_memory(cell);
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::linear::LinearTree;
+    use crate::tree::fixtures::{make_actor, make_actors, check_same_hits};
+
+    #[test]
+    fn resolution() {
+        let bounds = BoundingBox::new(Vec3::from_array([0.0; 3]), Vec3::from_array([10.0, 10.0, 10.0]));
+        assert_eq!(GridTree::get_resolution_for(&bounds, 0), [1, 1, 1]);
+        assert_eq!(GridTree::get_resolution_for(&bounds, 500), [10, 10, 10]);
+
+        // Flat bounds keep a single layer of cells
+        let bounds = BoundingBox::new(Vec3::from_array([0.0; 3]), Vec3::from_array([40.0, 20.0, 0.0]));
+        assert_eq!(GridTree::get_resolution_for(&bounds, 64), [32, 16, 1]);
+    }
+
+    #[test]
+    fn same_hits() {
+        let actors = make_actors();
+        let mut tree = GridTree::new();
+        let mut reference = LinearTree::new();
+        for actor in &actors {
+            assert!(tree.add_actor(Rc::clone(actor)));
+            reference.add_actor(Rc::clone(actor));
+        }
+        tree.rebuild();
+        assert!(tree.get_resolution().iter().product::<usize>() > 1);
+        check_same_hits(&tree, &reference);
+        check_same_hits(&tree.flatten(), &reference);
+
+        // Removed actors are gone from every cell, added ones go in the existing cells
+        for actor in actors.iter().step_by(3) {
+            assert!(tree.remove_actor(actor));
+            assert!(!tree.remove_actor(actor));
+            reference.remove_actor(actor);
+        }
+        for i in 0..4 {
+            let actor = make_actor([1.0 + 3.0 * i as f64, 5.0, 0.0], 1.0);
+            tree.add_actor(Rc::clone(&actor));
+            reference.add_actor(actor);
+        }
+        assert!(tree.modified);
+        check_same_hits(&tree, &reference);
+        check_same_hits(&tree.flatten(), &reference);
+
+        // An actor outside the grid rebuilds it
+        let actor = make_actor([30.0, 0.0, 0.0], 1.0);
+        tree.add_actor(Rc::clone(&actor));
+        reference.add_actor(actor);
+        assert!(!tree.modified);
+        check_same_hits(&tree, &reference);
+    }
+
+    #[test]
+    fn large_actors() {
+        // A ground much larger than the other actors doesn't stretch the cells
+        let actors = make_actors();
+        let ground = make_actor([0.0, 0.0, -1000.0], 999.0);
+        let mut tree = GridTree::new();
+        let mut reference = LinearTree::new();
+        for actor in actors.iter().chain(std::iter::once(&ground)) {
+            tree.add_actor(Rc::clone(actor));
+            reference.add_actor(Rc::clone(actor));
+        }
+        tree.rebuild();
+        assert_eq!(tree.large.len(), 1);
+        assert!(Rc::ptr_eq(&tree.large[0], &ground));
+        assert!(tree.get_bounds().get_p0().get_data()[2] > -10.0);
+        check_same_hits(&tree, &reference);
+        check_same_hits(&tree.flatten(), &reference);
+
+        assert!(tree.remove_actor(&ground));
+        assert!(tree.large.is_empty());
+    }
+}
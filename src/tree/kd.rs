@@ -0,0 +1,567 @@
+use std::rc::Rc;
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::boundingbox::BoundingBox;
+use crate::actor::Actor;
+use crate::tree::Tree;
+use crate::tree::flat::{FlatTree, FlatSource};
+use crate::tree::stats::{self, TreeStats};
+
+// Relative costs of visiting a node and of intersecting an actor, used by the surface area heuristic
+const TRAVERSAL_COST: f64 = 1.0;
+const INTERSECTION_COST: f64 = 4.0;
+// Discount on the cost of the splits that cut off empty space
+const EMPTY_BONUS: f64 = 0.5;
+
+struct KdNode<T>
+    where T: Float
+{
+    bounds: BoundingBox<T>, // The bounds of this node
+    axis: usize, // The axis of the split plane
+    position: T, // The position of the split plane along its axis
+    children: [Option<Box<KdNode<T>>>; 2], // The children below and above the split plane
+    actors: Vec<Rc<Actor<T>>> // The actors stored in the leaf nodes, those straddling a split plane are on both sides
+}
+
+// A kd-tree whose split planes are chosen with the surface area heuristic.
+// Actors added after the tree is built are sent down the existing split planes,
+// the tree is built again when their number doubles or when it is rebuilt.
+pub struct KdTree<T>
+    where T: Float
+{
+    root: KdNode<T>,
+    actors: Vec<Rc<Actor<T>>>, // All the bounded actors, to build the tree again
+    built_count: usize, // The number of actors when the tree was last built
+    modified: bool, // Whether actors were added or removed since the tree was last built
+    unbounded: Vec<Rc<Actor<T>>> // Actors with infinite extent (e.g. planes)
+}
+
+impl<T> KdNode<T>
+    where T: Float
+{
+    fn new(bounds: BoundingBox<T>, actors: Vec<Rc<Actor<T>>>) -> Self {
+        let children: [Option<Box<KdNode<T>>>; 2] = [None, None];
+        KdNode {
+            bounds,
+            axis: 0,
+            position: T::zero(),
+            children,
+            actors
+        }
+    }
+
+    fn build(bounds: BoundingBox<T>, actors: Vec<Rc<Actor<T>>>, depth: usize) -> Self {
+        let mut node = KdNode::new(bounds, actors);
+        if depth == 0 || node.actors.len() <= 1 {
+            return node;
+        }
+
+        let (axis, position) = match node.find_split() {
+            Some(split) => split,
+            None => return node
+        };
+
+        let mut below = Vec::new();
+        let mut above = Vec::new();
+        for actor in std::mem::take(&mut node.actors) {
+            let (min, max) = actor.hitable.get_bounds().get_axis_bounds(axis);
+            if max > position {
+                above.push(Rc::clone(&actor));
+            }
+            if min < position || max <= position {
+                below.push(actor);
+            }
+        }
+
+        let (below_bounds, above_bounds) = node.get_child_bounds(axis, position);
+        node.axis = axis;
+        node.position = position;
+        node.children[0] = Some(Box::new(KdNode::build(below_bounds, below, depth - 1)));
+        node.children[1] = Some(Box::new(KdNode::build(above_bounds, above, depth - 1)));
+        node
+    }
+
+    fn get_child_bounds(&self, axis: usize, position: T) -> (BoundingBox<T>, BoundingBox<T>) {
+        let mut below_p1 = Vec3::from_slice(self.bounds.get_p1().get_data());
+        below_p1.get_data_mut()[axis] = position;
+        let mut above_p0 = Vec3::from_slice(self.bounds.get_p0().get_data());
+        above_p0.get_data_mut()[axis] = position;
+        (
+            BoundingBox::new(Vec3::from_slice(self.bounds.get_p0().get_data()), below_p1),
+            BoundingBox::new(above_p0, Vec3::from_slice(self.bounds.get_p1().get_data()))
+        )
+    }
+
+    fn find_split(&self) -> Option<(usize, T)> {
+        // The split plane with the lowest cost, if any is cheaper than testing all the actors.
+        // The candidates are the sides of the actor bounds.
+        let traversal_cost = T::from(TRAVERSAL_COST).unwrap();
+        let intersection_cost = T::from(INTERSECTION_COST).unwrap();
+        let count = self.actors.len();
+        let area = self.bounds.get_area();
+        if area <= T::zero() {
+            return None;
+        }
+
+        let mut best = None;
+        let mut best_cost = intersection_cost * T::from(count).unwrap();
+        for axis in 0..3 {
+            let (node_min, node_max) = self.bounds.get_axis_bounds(axis);
+            let mut starts = Vec::with_capacity(count);
+            let mut ends = Vec::with_capacity(count);
+            let mut flats = Vec::new();
+            for actor in &self.actors {
+                let (min, max) = actor.hitable.get_bounds().get_axis_bounds(axis);
+                starts.push(min);
+                ends.push(max);
+                if min == max {
+                    flats.push(min);
+                }
+            }
+            starts.sort_by(|x, y| x.partial_cmp(y).unwrap());
+            ends.sort_by(|x, y| x.partial_cmp(y).unwrap());
+            flats.sort_by(|x, y| x.partial_cmp(y).unwrap());
+
+            for &position in starts.iter().chain(ends.iter()) {
+                if position <= node_min || position >= node_max {
+                    continue;
+                }
+                // Actors ending on the plane are only below it, those starting on it only above it,
+                // and flat actors lying on it are below it
+                let flat_count = flats.partition_point(|&x| x <= position) - flats.partition_point(|&x| x < position);
+                let below_count = starts.partition_point(|&x| x < position) + flat_count;
+                let above_count = count - ends.partition_point(|&x| x <= position);
+
+                let (below, above) = self.get_child_bounds(axis, position);
+                let below_cost = below.get_area() / area * T::from(below_count).unwrap();
+                let above_cost = above.get_area() / area * T::from(above_count).unwrap();
+                let bonus = if below_count == 0 || above_count == 0 {
+                    T::one() - T::from(EMPTY_BONUS).unwrap()
+                } else {
+                    T::one()
+                };
+                let cost = traversal_cost + bonus * intersection_cost * (below_cost + above_cost);
+                if cost < best_cost {
+                    best_cost = cost;
+                    best = Some((axis, position));
+                }
+            }
+        }
+        best
+    }
+
+    fn insert(&mut self, actor: Rc<Actor<T>>) {
+        let (min, max) = actor.hitable.get_bounds().get_axis_bounds(self.axis);
+        match &mut self.children {
+            [Some(below), Some(above)] => {
+                if max > self.position {
+                    above.insert(Rc::clone(&actor));
+                }
+                if min < self.position || max <= self.position {
+                    below.insert(actor);
+                }
+            },
+            _ => {
+                self.actors.push(actor);
+            }
+        }
+    }
+
+    fn remove(&mut self, actor: &Rc<Actor<T>>) {
+        self.actors.retain(|current| !Rc::ptr_eq(current, actor));
+        for child in self.children.iter_mut().flatten() {
+            child.remove(actor);
+        }
+    }
+
+    fn get_children(&self, ray: &Ray<T>) -> Option<(&KdNode<T>, &KdNode<T>, T)> {
+        // The children nearest first, and the distance where the ray crosses the split plane
+        let (below, above) = match &self.children {
+            [Some(below), Some(above)] => (below, above),
+            _ => return None
+        };
+
+        let origin = ray.get_origin().get_data()[self.axis];
+        let direction = ray.get_direction().get_data()[self.axis];
+        let t_plane = if direction != T::zero() {
+            (self.position - origin) / direction
+        } else {
+            T::infinity()
+        };
+        if origin < self.position || (origin == self.position && direction <= T::zero()) {
+            Some((below, above, t_plane))
+        } else {
+            Some((above, below, t_plane))
+        }
+    }
+
+    fn lies_in_plane(&self, ray: &Ray<T>) -> bool {
+        ray.get_direction().get_data()[self.axis] == T::zero() && ray.get_origin().get_data()[self.axis] == self.position
+    }
+
+    fn get_hit(&self, ray: &Ray<T>, t_min: T, t_max: T, t_near: T, t_far: T) -> Option<(&Rc<Actor<T>>, Hit<T>)> {
+        // The ray is inside this node between t_near and t_far
+        if t_near > t_max {
+            return None;
+        }
+        stats::count_traversal_step();
+
+        let (near, far, t_plane) = match self.get_children(ray) {
+            Some(children) => children,
+            None => {
+                let mut t_max = t_max;
+                let mut result = None;
+                for actor in &self.actors {
+                    stats::count_primitive_test();
+                    if let Some(hit) = actor.hit(ray, t_min, t_max) {
+                        t_max = hit.t;
+                        result = Some((actor, hit));
+                    }
+                }
+                return result;
+            }
+        };
+
+        // A ray lying in the split plane goes through both children all along,
+        // e.g. along the edge of actors starting or ending on the plane
+        if self.lies_in_plane(ray) {
+            let mut t_max = t_max;
+            let mut result = near.get_hit(ray, t_min, t_max, t_near, t_far);
+            if let Some((_, hit)) = &result {
+                t_max = hit.t;
+            }
+            if let Some(found) = far.get_hit(ray, t_min, t_max, t_near, t_far) {
+                result = Some(found);
+            }
+            return result;
+        }
+
+        // The ray only goes through one side of the split plane
+        if t_plane > t_far || t_plane <= T::zero() {
+            return near.get_hit(ray, t_min, t_max, t_near, t_far);
+        }
+        if t_plane < t_near {
+            return far.get_hit(ray, t_min, t_max, t_near, t_far);
+        }
+
+        // A hit beyond the split plane, on an actor straddling it, could hide a closer one in the far child
+        let mut t_max = t_max;
+        let mut result = near.get_hit(ray, t_min, t_max, t_near, t_plane);
+        if let Some((_, hit)) = &result {
+            if hit.t <= t_plane {
+                return result;
+            }
+            t_max = hit.t;
+        }
+        if let Some(found) = far.get_hit(ray, t_min, t_max, t_plane, t_far) {
+            result = Some(found);
+        }
+        result
+    }
+
+    fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T, t_near: T, t_far: T) -> bool {
+        stats::count_traversal_step();
+        let (near, far, t_plane) = match self.get_children(ray) {
+            Some(children) => children,
+            None => {
+                return self.actors.iter().any(|actor| {
+                    stats::count_primitive_test();
+                    actor.any_hit(ray, t_min, t_max)
+                });
+            }
+        };
+
+        if self.lies_in_plane(ray) {
+            return near.any_hit(ray, t_min, t_max, t_near, t_far) || far.any_hit(ray, t_min, t_max, t_near, t_far);
+        }
+        if t_plane > t_far || t_plane <= T::zero() {
+            return near.any_hit(ray, t_min, t_max, t_near, t_far);
+        }
+        if t_plane < t_near {
+            return far.any_hit(ray, t_min, t_max, t_near, t_far);
+        }
+        near.any_hit(ray, t_min, t_max, t_near, t_plane) || far.any_hit(ray, t_min, t_max, t_plane, t_far)
+    }
+
+    fn collect_stats(&self, level: usize, stats: &mut TreeStats) {
+        stats.add_node(level);
+        stats.memory += std::mem::size_of::<Self>() + stats::get_vec_memory(&self.actors);
+        if self.children.iter().all(|child| child.is_none()) {
+            stats.add_leaf(level, self.actors.len());
+        }
+        for child in self.children.iter().flatten() {
+            child.collect_stats(level + 1, stats);
+        }
+    }
+}
+
+impl<T> FlatSource<T> for KdNode<T>
+    where T: Float
+{
+    fn get_node_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn get_node_actors(&self) -> Vec<&Rc<Actor<T>>> {
+        self.actors.iter().collect()
+    }
+
+    fn get_node_children(&self) -> Vec<&Self> {
+        self.children.iter().flatten().map(|child| &**child).collect()
+    }
+
+    fn get_node_split(&self) -> Option<(usize, T)> {
+        match &self.children {
+            [Some(_), Some(_)] => Some((self.axis, self.position)),
+            _ => None
+        }
+    }
+}
+
+impl<T> KdTree<T>
+    where T: Float
+{
+    pub fn new() -> Self {
+        let bounds = BoundingBox::<T>::new(Vec3::<T>::new(), Vec3::<T>::new());
+        KdTree {
+            root: KdNode::new(bounds, Vec::new()),
+            actors: Vec::new(),
+            built_count: 0,
+            modified: false,
+            unbounded: Vec::new()
+        }
+    }
+
+    pub fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.root.bounds
+    }
+
+    fn get_actor_bounds(&self) -> BoundingBox<T> {
+        let mut bounds = BoundingBox::<T>::new(Vec3::<T>::new(), Vec3::<T>::new());
+        if let Some((first, rest)) = self.actors.split_first() {
+            bounds = first.hitable.get_bounds().clone();
+            for actor in rest {
+                bounds.expand(actor.hitable.get_bounds());
+            }
+        }
+        bounds
+    }
+
+    fn build(&mut self) {
+        let bounds = self.get_actor_bounds();
+
+        // About 8 + 1.3 log2(n) levels
+        let log_count = (std::mem::size_of::<usize>() * 8) as u32 - self.actors.len().leading_zeros();
+        let max_depth = 8 + 13 * log_count as usize / 10;
+        self.root = KdNode::build(bounds, self.actors.clone(), max_depth);
+        self.built_count = self.actors.len();
+        self.modified = false;
+    }
+}
+
+impl<T> Tree<T> for KdTree<T>
+    where T: Float
+{
+    fn add_actor(&mut self, actor: Rc<Actor<T>>) -> bool {
+        // Unbounded actors can't be split, keep them aside and always test them
+        if !actor.hitable.get_bounds().is_finite() {
+            self.unbounded.push(actor);
+            return true;
+        }
+
+        self.actors.push(Rc::clone(&actor));
+        self.modified = true;
+        if self.actors.len() >= 2 * self.built_count {
+            self.build();
+        } else {
+            // The outer nodes extend to the new bounds of the root
+            self.root.bounds.expand(actor.hitable.get_bounds());
+            self.root.insert(actor);
+        }
+        true
+    }
+
+    fn remove_actor(&mut self, actor: &Rc<Actor<T>>) -> bool {
+        let count = self.unbounded.len();
+        self.unbounded.retain(|current| !Rc::ptr_eq(current, actor));
+        if self.unbounded.len() < count {
+            return true;
+        }
+
+        let count = self.actors.len();
+        self.actors.retain(|current| !Rc::ptr_eq(current, actor));
+        if self.actors.len() == count {
+            return false;
+        }
+        self.root.remove(actor);
+        self.modified = true;
+        true
+    }
+
+    fn refit(&mut self) {
+        // The split planes stay where they are, only the root shrinks to the remaining actors
+        self.root.bounds = self.get_actor_bounds();
+    }
+
+    fn rebuild(&mut self) {
+        // The split planes only fit the actors the tree was built with
+        if self.modified {
+            self.build();
+        }
+    }
+
+    fn get_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(Rc<Actor<T>>, Hit<T>)> {
+        let mut t_max = t_max;
+        let mut result : Option<(Rc<Actor<T>>, Hit<T>)> = None;
+        stats::count_ray();
+
+        for actor in self.unbounded.iter() {
+            stats::count_primitive_test();
+            if let Some(hit) = actor.hit(ray, t_min, t_max) {
+                t_max = hit.t;
+                result = Some((Rc::clone(actor), hit));
+            }
+        }
+
+        stats::count_box_test();
+        if let Some((t_near, t_far)) = self.root.bounds.intersect(ray, t_min, t_max) {
+            if let Some((actor, hit)) = self.root.get_hit(ray, t_min, t_max, t_near, t_far) {
+                result = Some((Rc::clone(actor), hit));
+            }
+        }
+
+        result
+    }
+
+    fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        stats::count_ray();
+        if self.unbounded.iter().any(|actor| {
+            stats::count_primitive_test();
+            actor.any_hit(ray, t_min, t_max)
+        }) {
+            return true;
+        }
+
+        stats::count_box_test();
+        match self.root.bounds.intersect(ray, t_min, t_max) {
+            Some((t_near, t_far)) => self.root.any_hit(ray, t_min, t_max, t_near, t_far),
+            None => false
+        }
+    }
+
+    fn flatten(&self) -> FlatTree<T> {
+        FlatTree::from_nodes(&self.root, &self.unbounded)
+    }
+
+    fn get_stats(&self) -> TreeStats {
+        let mut stats = TreeStats::new();
+        stats.unbounded = self.unbounded.len();
+        self.root.collect_stats(0, &mut stats);
+        stats.memory += std::mem::size_of::<Self>() - std::mem::size_of::<KdNode<T>>()
+            + stats::get_vec_memory(&self.actors) + stats::get_vec_memory(&self.unbounded);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::linear::LinearTree;
+    use crate::tree::fixtures::{make_actor, make_actors, check_same_hits};
+    use crate::vector::Vec3;
+    use crate::constants::Axis;
+    use crate::hitable::primitive::Rectangle;
+    use crate::hitable::transform::Translation;
+    use crate::material::plain::PlainMaterial;
+    use crate::texture::uniform::UniformTexture;
+
+    #[test]
+    fn same_hits() {
+        let actors = make_actors();
+        let mut tree = KdTree::new();
+        let mut reference = LinearTree::new();
+        for actor in &actors {
+            assert!(tree.add_actor(Rc::clone(actor)));
+            reference.add_actor(Rc::clone(actor));
+        }
+        tree.rebuild();
+        check_same_hits(&tree, &reference);
+        check_same_hits(&tree.flatten(), &reference);
+
+        // Removed actors are gone from every leaf, added ones go down the existing splits
+        for actor in actors.iter().step_by(3) {
+            assert!(tree.remove_actor(actor));
+            assert!(!tree.remove_actor(actor));
+            reference.remove_actor(actor);
+        }
+        for i in 0..4 {
+            let actor = make_actor([-6.0 + 8.0 * i as f64, 5.0, 2.0], 1.5);
+            tree.add_actor(Rc::clone(&actor));
+            reference.add_actor(actor);
+        }
+        assert!(tree.modified);
+        check_same_hits(&tree, &reference);
+        check_same_hits(&tree.flatten(), &reference);
+
+        // Refitting keeps the split planes, rebuilding chooses them again
+        tree.refit();
+        assert!(tree.modified);
+        check_same_hits(&tree, &reference);
+        tree.rebuild();
+        assert!(!tree.modified);
+        check_same_hits(&tree, &reference);
+    }
+
+    #[test]
+    fn empty_space() {
+        // The far actor is cut off from the cluster by the first split plane
+        let mut tree = KdTree::new();
+        for i in 0..8 {
+            let center = [(i % 2) as f64, ((i / 2) % 2) as f64, (i / 4) as f64];
+            tree.add_actor(make_actor(center, 0.25));
+        }
+        let far = make_actor([50.0, 0.0, 0.0], 0.25);
+        tree.add_actor(Rc::clone(&far));
+        tree.rebuild();
+
+        assert_eq!(tree.root.axis, 0);
+        match &tree.root.children {
+            [Some(below), Some(above)] => {
+                assert!(!below.actors.iter().any(|actor| Rc::ptr_eq(actor, &far)));
+                assert_eq!(above.actors.len(), 1);
+                assert!(Rc::ptr_eq(&above.actors[0], &far));
+            },
+            _ => {
+                assert!(false);
+            }
+        }
+    }
+
+    #[test]
+    fn ray_in_split_plane() {
+        // A rectangle facing X whose bounds start on the split plane y = -4, the plane of the first row
+        // of rays along X in check_same_hits. Those rays graze its edge, only the child above has it.
+        let hitable = Box::new(Rectangle::new(2.0, Axis::Y, 2.0, Axis::Z));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, -3.0, 0.0])));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+        let grazed = Rc::new(Actor::new(hitable, Box::new(PlainMaterial::new(texture))));
+        let below = make_actor([0.0, -5.5, 0.0], 1.0);
+
+        let mut tree = KdTree::new();
+        let mut reference = LinearTree::new();
+        for actor in &[grazed, below] {
+            tree.add_actor(Rc::clone(actor));
+            reference.add_actor(Rc::clone(actor));
+        }
+        tree.rebuild();
+        assert_eq!((tree.root.axis, tree.root.position), (1, -4.0));
+
+        let ray = Ray::from_array([-10.0, -4.0, 0.0], [1.0, 0.0, 0.0]);
+        assert!(reference.get_hit(&ray, 0.0, 100.0).is_some());
+        check_same_hits(&tree, &reference);
+        check_same_hits(&tree.flatten(), &reference);
+    }
+}
@@ -7,6 +7,8 @@ use crate::hit::Hit;
 use crate::boundingbox::BoundingBox;
 use crate::actor::Actor;
 use crate::tree::Tree;
+use crate::tree::flat::FlatTree;
+use crate::tree::stats::{self, TreeStats};
 
 pub struct LinearTree<T>
     where T: Float
@@ -33,12 +35,25 @@ impl<T> Tree<T> for LinearTree<T>
         return true;
     }
 
+    fn remove_actor(&mut self, actor: &Rc<Actor<T>>) -> bool {
+        let count = self.actors.len();
+        self.actors.retain(|current| !Rc::ptr_eq(current, actor));
+        self.actors.len() < count
+    }
+
+    fn refit(&mut self) {
+        // There are no bounds to update
+    }
+
     fn get_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(Rc<Actor<T>>, Hit<T>)> {
         let mut t_max = t_max;
         let mut result : Option<(Rc<Actor<T>>, Hit<T>)> = None;
+        stats::count_ray();
+        stats::count_traversal_step();
 
         for i in 0..self.actors.len() {
-            if let Some(hit) = self.actors[i].hitable.hit(ray, t_min, t_max) {
+            stats::count_primitive_test();
+            if let Some(hit) = self.actors[i].hit(ray, t_min, t_max) {
                 t_max = hit.t;
                 result = Some((Rc::clone(&self.actors[i]), hit));
             }
@@ -46,4 +61,26 @@ impl<T> Tree<T> for LinearTree<T>
 
         result
     }
+
+    fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        stats::count_ray();
+        stats::count_traversal_step();
+        self.actors.iter().any(|actor| {
+            stats::count_primitive_test();
+            actor.any_hit(ray, t_min, t_max)
+        })
+    }
+
+    fn flatten(&self) -> FlatTree<T> {
+        FlatTree::from_actors(&self.actors)
+    }
+
+    fn get_stats(&self) -> TreeStats {
+        // A single leaf with all the actors
+        let mut stats = TreeStats::new();
+        stats.add_node(0);
+        stats.add_leaf(0, self.actors.len());
+        stats.memory = std::mem::size_of::<Self>() + stats::get_vec_memory(&self.actors);
+        stats
+    }
 }
@@ -4,21 +4,107 @@ use crate::float::Float;
 use crate::ray::Ray;
 use crate::hit::Hit;
 use crate::actor::Actor;
+use crate::tree::flat::FlatTree;
+use crate::tree::stats::TreeStats;
 
 pub mod linear;
 pub mod oct;
 pub mod binary;
+pub mod kd;
+pub mod grid;
+pub mod stats;
+pub mod flat;
 
 pub trait Tree<T>
     where T: Float
 {
     fn add_actor(&mut self, actor: Rc<Actor<T>>) -> bool;
 
+    // Actors are identified by their pointer, returns whether the actor was found
+    fn remove_actor(&mut self, actor: &Rc<Actor<T>>) -> bool;
+
+    // Shrinks the node bounds to the actors they still contain
+    fn refit(&mut self);
+
+    // Builds the tree again for the actors it holds, e.g. once they are all known.
+    // Only needed by the trees whose structure is chosen from the actors, the others are refitted.
+    fn rebuild(&mut self) {
+        self.refit();
+    }
+
     fn get_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(Rc<Actor<T>>, Hit<T>)>;
+
+    // Stops at the first hit found, not necessarily the closest one
+    fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool;
+
+    // A compacted copy of the tree, faster to traverse but not meant to be edited
+    fn flatten(&self) -> FlatTree<T>;
+
+    fn get_stats(&self) -> TreeStats;
 }
 
 pub enum TreeType {
     Linear,
     Binary,
-    Oct
+    Oct,
+    Kd,
+    Grid
+}
+
+// Scenes and checks shared by the tests of the different trees
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+    use crate::vector::Vec3;
+    use crate::hitable::primitive::Sphere;
+    use crate::hitable::transform::Translation;
+    use crate::material::plain::PlainMaterial;
+    use crate::texture::uniform::UniformTexture;
+
+    pub fn make_actor(center: [f64; 3], radius: f64) -> Rc<Actor<f64>> {
+        let hitable = Box::new(Translation::new(Box::new(Sphere::new(radius)), Vec3::from_array(center)));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+        Rc::new(Actor::new(hitable, Box::new(PlainMaterial::new(texture))))
+    }
+
+    pub fn make_actors() -> Vec<Rc<Actor<f64>>> {
+        // Small spheres on a grid, and a few large ones straddling most split planes and cells
+        let mut actors = Vec::new();
+        for i in 0..5 {
+            for j in 0..5 {
+                for k in 0..2 {
+                    let center = [3.0 * i as f64, 3.0 * j as f64, -3.0 * k as f64];
+                    actors.push(make_actor(center, 0.5 + 0.1 * ((i + j + k) % 3) as f64));
+                }
+            }
+        }
+        actors.push(make_actor([6.0, 6.0, -1.5], 4.0));
+        actors.push(make_actor([1.5, 10.5, -1.5], 2.5));
+        actors
+    }
+
+    pub fn check_same_hits(tree: &dyn Tree<f64>, reference: &dyn Tree<f64>) {
+        let directions = [[0.1, 0.2, -1.0], [0.0, 0.0, -1.0], [1.0, 0.0, 0.0], [-0.3, -1.0, 0.2]];
+        for direction in &directions {
+            for i in 0..24 {
+                for j in 0..24 {
+                    let u = -4.0 + 0.9 * i as f64;
+                    let v = -4.0 + 0.9 * j as f64;
+                    let origin = [u - 10.0 * direction[0], v - 10.0 * direction[1], 0.5 * u - 10.0 * direction[2]];
+                    let ray = Ray::from_array(origin, *direction);
+                    match (tree.get_hit(&ray, 0.0, 100.0), reference.get_hit(&ray, 0.0, 100.0)) {
+                        (Some((actor, hit)), Some((reference_actor, reference_hit))) => {
+                            assert!(Rc::ptr_eq(&actor, &reference_actor));
+                            assert_eq!(hit.t, reference_hit.t);
+                        },
+                        (None, None) => {},
+                        _ => {
+                            assert!(false);
+                        }
+                    }
+                    assert_eq!(tree.any_hit(&ray, 0.0, 100.0), reference.any_hit(&ray, 0.0, 100.0));
+                }
+            }
+        }
+    }
 }
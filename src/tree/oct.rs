@@ -7,25 +7,70 @@ use crate::hit::Hit;
 use crate::boundingbox::BoundingBox;
 use crate::actor::Actor;
 use crate::tree::Tree;
+use crate::tree::flat::{FlatTree, FlatSource};
+use crate::tree::stats::{self, TreeStats};
+
+#[derive(Clone, Copy)]
+pub struct OctreeOptions<T>
+    where T: Float
+{
+    // The children bounds are their cell scaled by this factor around its center.
+    // Loose children (> 1) accept actors straddling the boundaries of their cell.
+    pub looseness: T,
+    pub max_depth: usize, // Nodes at this depth store all their actors
+    pub leaf_size: usize // Nodes are only split when they hold more actors than this
+}
+
+impl<T> OctreeOptions<T>
+    where T: Float
+{
+    pub fn new() -> Self {
+        OctreeOptions {
+            looseness: T::one(),
+            max_depth: 16,
+            leaf_size: 0
+        }
+    }
+}
 
 pub struct Octree<T>
     where T: Float
 {
     bounds: BoundingBox<T>, // The bounds of this node
+    cell: BoundingBox<T>, // The part of space this node splits among its children
+    depth: usize,
+    split: bool, // Whether the actors are sent down to the children
+    options: OctreeOptions<T>,
     pub children: [Option<Box<Octree<T>>>; 8], // The children octrees
-    pub actors: Vec<Rc<Actor<T>>> // The actors that are too large to be placed in the children nodes
+    pub actors: Vec<Rc<Actor<T>>>, // The actors that are too large to be placed in the children nodes
+    pub unbounded: Vec<Rc<Actor<T>>> // Actors with infinite extent (e.g. planes), only used by the root node
 }
 
 impl<T> Octree<T>
     where T: Float
 {
     pub fn new(bounds: BoundingBox<T>) -> Self {
+        Octree::with_options(bounds, OctreeOptions::new())
+    }
+
+    pub fn with_options(bounds: BoundingBox<T>, options: OctreeOptions<T>) -> Self {
+        let cell = bounds.clone();
+        Octree::new_node(bounds, cell, 0, options)
+    }
+
+    fn new_node(bounds: BoundingBox<T>, cell: BoundingBox<T>, depth: usize, options: OctreeOptions<T>) -> Self {
         let children: [Option<Box<Octree<T>>>; 8] = [None, None, None, None, None, None, None, None];
         let actors = Vec::new();
+        let unbounded = Vec::new();
         Octree {
             bounds,
+            cell,
+            depth,
+            split: false,
+            options,
             children,
-            actors
+            actors,
+            unbounded
         }
     }
 
@@ -33,6 +78,138 @@ impl<T> Octree<T>
         &self.bounds
     }
 
+    pub fn get_options(&self) -> &OctreeOptions<T> {
+        &self.options
+    }
+
+    fn collect_stats(&self, stats: &mut TreeStats) {
+        stats.add_node(self.depth);
+        stats.memory += std::mem::size_of::<Self>() + stats::get_vec_memory(&self.actors) + stats::get_vec_memory(&self.unbounded);
+        if !self.actors.is_empty() || self.children.iter().all(|child| child.is_none()) {
+            stats.add_leaf(self.depth, self.actors.len());
+        }
+        for child in self.children.iter().flatten() {
+            child.collect_stats(stats);
+        }
+    }
+
+    fn insert(&mut self, actor: Rc<Actor<T>>) {
+        // Leaves keep their actors until they hold more than the leaf size
+        if self.depth >= self.options.max_depth || (!self.split && self.actors.len() < self.options.leaf_size) {
+            self.actors.push(actor);
+            return;
+        }
+
+        if !self.split {
+            self.split = true;
+            let actors = std::mem::take(&mut self.actors);
+            for current_actor in actors {
+                self.insert_below(current_actor);
+            }
+        }
+        self.insert_below(actor);
+    }
+
+    fn insert_below(&mut self, actor: Rc<Actor<T>>) {
+        // The actor goes to the child whose cell contains its center, if it fits in its bounds
+        let actor_bounds = actor.hitable.get_bounds();
+        let half = T::from(0.5).unwrap();
+        let mut index = 0;
+        for axis in 0..3 {
+            let (min, max) = actor_bounds.get_axis_bounds(axis);
+            let (cell_min, cell_max) = self.cell.get_axis_bounds(axis);
+            if half * (min + max) >= half * (cell_min + cell_max) {
+                index += 1 << (2 - axis);
+            }
+        }
+
+        match &mut self.children[index] {
+            Some(child) => {
+                if child.get_bounds().contains(actor_bounds) {
+                    child.insert(actor);
+                    return;
+                }
+            },
+            None => {
+                let cell = self.get_child_bounds(index);
+                let bounds = self.get_loose_bounds(&cell);
+                if bounds.contains(actor_bounds) {
+                    let mut child = Octree::new_node(bounds, cell, self.depth + 1, self.options);
+                    child.insert(actor);
+                    self.children[index] = Some(Box::new(child));
+                    return;
+                }
+            }
+        }
+
+        // If the actor is too large for the child, add it to this node.
+        self.actors.push(actor);
+    }
+
+    fn grow(&mut self, target: &BoundingBox<T>) -> bool {
+        // An empty tree is simply moved around the target
+        if self.is_empty() {
+            let mut cell = target.clone();
+            cell.make_cube();
+            let pad = T::from(0.1).unwrap() * cell.get_axis_length(0);
+            for axis in 0..3 {
+                cell.pad_axis(pad, axis);
+            }
+            let unbounded = std::mem::take(&mut self.unbounded);
+            *self = Octree::with_options(cell, self.options);
+            self.unbounded = unbounded;
+            return true;
+        }
+
+        // Otherwise the root becomes one of the octants of a cell twice as large,
+        // extended towards the target on each axis
+        let half = T::from(0.5).unwrap();
+        let mut p0 = Vec3::from_slice(self.cell.get_p0().get_data());
+        let mut p1 = Vec3::from_slice(self.cell.get_p1().get_data());
+        let mut index = 0;
+        for axis in 0..3 {
+            let (min, max) = self.cell.get_axis_bounds(axis);
+            let length = max - min;
+            if length <= T::zero() {
+                return false;
+            }
+            let (target_min, target_max) = target.get_axis_bounds(axis);
+            if half * (target_min + target_max) < half * (min + max) {
+                p0.get_data_mut()[axis] = min - length;
+                index += 1 << (2 - axis);
+            } else {
+                p1.get_data_mut()[axis] = max + length;
+            }
+        }
+
+        let cell = BoundingBox::new(p0, p1);
+        let root = Octree::new_node(cell.clone(), cell, 0, self.options);
+        let mut child = std::mem::replace(self, root);
+        self.unbounded = std::mem::take(&mut child.unbounded);
+        self.split = true;
+        child.bounds = self.get_loose_bounds(&child.cell);
+        child.increase_depth();
+        self.children[index] = Some(Box::new(child));
+        true
+    }
+
+    fn increase_depth(&mut self) {
+        self.depth += 1;
+        for child in self.children.iter_mut().flatten() {
+            child.increase_depth();
+        }
+    }
+
+    fn get_loose_bounds(&self, cell: &BoundingBox<T>) -> BoundingBox<T> {
+        let mut bounds = cell.clone();
+        let half = T::from(0.5).unwrap();
+        for axis in 0..3 {
+            let pad = half * (self.options.looseness - T::one()) * cell.get_axis_length(axis);
+            bounds.pad_axis(pad, axis);
+        }
+        bounds
+    }
+
     fn get_child_bounds(&self, child_index: usize) -> BoundingBox<T> {
         let k = child_index % 2;
         let j = (child_index / 2) % 2;
@@ -44,9 +221,9 @@ impl<T> Octree<T>
 
         let half = T::from(0.5).unwrap();
 
-        let (min_x, max_x) = self.get_bounds().get_axis_bounds(0);
-        let (min_y, max_y) = self.get_bounds().get_axis_bounds(1);
-        let (min_z, max_z) = self.get_bounds().get_axis_bounds(2);
+        let (min_x, max_x) = self.cell.get_axis_bounds(0);
+        let (min_y, max_y) = self.cell.get_axis_bounds(1);
+        let (min_z, max_z) = self.cell.get_axis_bounds(2);
         let len = [half * (max_x - min_x), half * (max_y - min_y), half * (max_z - min_z)];
 
         let offset = Vec3::<T>::from_array([len[0] * i, len[1] * j, len[2] * k]);
@@ -55,6 +232,90 @@ impl<T> Octree<T>
         let p1 = &p0 + Vec3::from_slice(&len);
         BoundingBox::<T>::new(p0, p1)
     }
+
+    fn get_hit_inside(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(&Rc<Actor<T>>, Hit<T>)> {
+        // The ray is already known to hit the bounds of this node
+        let mut t_max = t_max;
+        let mut result : Option<(&Rc<Actor<T>>, Hit<T>)> = None;
+        stats::count_traversal_step();
+
+        for i in 0..self.actors.len() {
+            stats::count_primitive_test();
+            if let Some(hit) = self.actors[i].hit(ray, t_min, t_max) {
+                t_max = hit.t;
+                result = Some((&self.actors[i], hit));
+            }
+        }
+
+        // Sort the children hit by the ray by the distance where it enters them
+        let mut order = [(T::zero(), 0); 8];
+        let mut count = 0;
+        for i in 0..8 {
+            if let Some(child) = &self.children[i] {
+                stats::count_box_test();
+                if let Some((t_enter, _)) = child.get_bounds().intersect(ray, t_min, t_max) {
+                    let mut j = count;
+                    while j > 0 && order[j - 1].0 > t_enter {
+                        order[j] = order[j - 1];
+                        j -= 1;
+                    }
+                    order[j] = (t_enter, i);
+                    count += 1;
+                }
+            }
+        }
+
+        for &(t_enter, i) in &order[0..count] {
+            // The remaining children are all further than the current hit
+            if t_enter > t_max {
+                break;
+            }
+            if let Some(child) = &self.children[i] {
+                if let Some((actor, hit)) = child.get_hit_inside(ray, t_min, t_max) {
+                    t_max = hit.t;
+                    result = Some((actor, hit));
+                }
+            }
+        }
+
+        result
+    }
+
+    fn any_hit_inside(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        // The ray is already known to hit the bounds of this node
+        stats::count_traversal_step();
+        if self.actors.iter().any(|actor| {
+            stats::count_primitive_test();
+            actor.any_hit(ray, t_min, t_max)
+        }) {
+            return true;
+        }
+
+        self.children.iter().flatten().any(|child| {
+            stats::count_box_test();
+            child.get_bounds().hit(ray, t_min, t_max) && child.any_hit_inside(ray, t_min, t_max)
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.actors.is_empty() && self.children.iter().all(|child| child.is_none())
+    }
+}
+
+impl<T> FlatSource<T> for Octree<T>
+    where T: Float
+{
+    fn get_node_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn get_node_actors(&self) -> Vec<&Rc<Actor<T>>> {
+        self.actors.iter().collect()
+    }
+
+    fn get_node_children(&self) -> Vec<&Self> {
+        self.children.iter().flatten().map(|child| &**child).collect()
+    }
 }
 
 impl<T> Tree<T> for Octree<T>
@@ -63,70 +324,111 @@ impl<T> Tree<T> for Octree<T>
     fn add_actor(&mut self, actor: Rc<Actor<T>>) -> bool {
         let actor_bounds = actor.hitable.get_bounds();
 
-        // If this node can't fully contain the actor, do nothing
-        if !self.bounds.contains(actor_bounds) {
-            return false;
+        // Unbounded actors can't fit in any node, keep them aside and always test them
+        if !actor_bounds.is_finite() {
+            self.unbounded.push(actor);
+            return true;
         }
 
-        // If one of the current/potential children of this node can fully
-        // contain the actor, add it to that node
-        for i in 0..8 {
-            let child = &mut self.children[i];
-            match child {
-                Some(node) => {
-                    if node.get_bounds().contains(actor_bounds) {
-                        node.add_actor(actor);
-                        return true;
-                    }
-                },
-                None => {
-                    let child_bounds = self.get_child_bounds(i);
-                    if child_bounds.contains(actor_bounds) {
-                        let mut node = Octree::<T>::new(child_bounds);
-                        node.add_actor(actor);
-                        self.children[i] = Some(Box::new(node));
-                        return true;
-                    }
-                }
+        // Grow the tree until it contains the actor, it fails only if the root can't grow
+        while !self.bounds.contains(actor_bounds) {
+            if !self.grow(actor_bounds) {
+                return false;
             }
         }
 
-        // If the actor is too large for any of the children, add it to this node.
-        self.actors.push(actor);
+        self.insert(actor);
         return true;
     }
 
-    fn get_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(Rc<Actor<T>>, Hit<T>)> {
-        if !self.get_bounds().hit(ray, t_min, t_max) {
-            return None;
+    fn remove_actor(&mut self, actor: &Rc<Actor<T>>) -> bool {
+        let count = self.unbounded.len() + self.actors.len();
+        self.unbounded.retain(|current| !Rc::ptr_eq(current, actor));
+        self.actors.retain(|current| !Rc::ptr_eq(current, actor));
+        if self.unbounded.len() + self.actors.len() < count {
+            return true;
+        }
+
+        // Actors are only ever placed in the children that fully contain them
+        let actor_bounds = actor.hitable.get_bounds();
+        for i in 0..8 {
+            let removed = match &mut self.children[i] {
+                Some(child) => child.get_bounds().contains(actor_bounds) && child.remove_actor(actor),
+                None => false
+            };
+            if removed {
+                // Prune the nodes left without actors
+                if matches!(&self.children[i], Some(child) if child.is_empty()) {
+                    self.children[i] = None;
+                }
+                return true;
+            }
         }
+        false
+    }
+
+    fn refit(&mut self) {
+        // The nodes split space at fixed positions, their bounds never change.
+        // Moved actors are removed and added again instead.
+    }
 
+    fn get_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(Rc<Actor<T>>, Hit<T>)> {
         let mut t_max = t_max;
-        let mut result : Option<(Rc<Actor<T>>, Hit<T>)> = None;
+        let mut result : Option<(&Rc<Actor<T>>, Hit<T>)> = None;
+        stats::count_ray();
 
-        for i in 0..self.actors.len() {
-            if let Some(hit) = self.actors[i].hitable.hit(ray, t_min, t_max) {
+        for i in 0..self.unbounded.len() {
+            stats::count_primitive_test();
+            if let Some(hit) = self.unbounded[i].hit(ray, t_min, t_max) {
                 t_max = hit.t;
-                result = Some((Rc::clone(&self.actors[i]), hit));
+                result = Some((&self.unbounded[i], hit));
             }
         }
 
-        for i in 0..8 {
-            if let Some(child) = &self.children[i] {
-                if let Some((actor, hit)) = child.get_hit(ray, t_min, t_max) {
-                    t_max = hit.t;
-                    result = Some((actor, hit));
-                }
+        stats::count_box_test();
+        if self.get_bounds().hit(ray, t_min, t_max) {
+            if let Some(found) = self.get_hit_inside(ray, t_min, t_max) {
+                result = Some(found);
             }
         }
 
-        result
+        // Only the closest actor is cloned, not every closer one found on the way
+        result.map(|(actor, hit)| (Rc::clone(actor), hit))
+    }
+
+    fn any_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        stats::count_ray();
+        if self.unbounded.iter().any(|actor| {
+            stats::count_primitive_test();
+            actor.any_hit(ray, t_min, t_max)
+        }) {
+            return true;
+        }
+
+        stats::count_box_test();
+        self.get_bounds().hit(ray, t_min, t_max) && self.any_hit_inside(ray, t_min, t_max)
+    }
+
+    fn flatten(&self) -> FlatTree<T> {
+        FlatTree::from_nodes(self, &self.unbounded)
+    }
+
+    fn get_stats(&self) -> TreeStats {
+        let mut stats = TreeStats::new();
+        stats.unbounded = self.unbounded.len();
+        self.collect_stats(&mut stats);
+        stats
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hitable::primitive::Sphere;
+    use crate::hitable::transform::Translation;
+    use crate::material::plain::PlainMaterial;
+    use crate::texture::uniform::UniformTexture;
+    use crate::tree::fixtures::make_actor;
 
     #[test]
     fn child_bounds() {
@@ -158,4 +460,138 @@ mod tests {
         }
         assert_eq!(node.get_bounds().get_volume(), children_volume);
     }
+
+    #[test]
+    fn any_hit() {
+        let bounds = BoundingBox::new(Vec3::from_array([-16.0; 3]), Vec3::from_array([16.0; 3]));
+        let mut tree = Octree::<f64>::new(bounds);
+        for i in 0..4 {
+            let translation = Vec3::from_array([4.0 * i as f64, 0.0, 0.0]);
+            let hitable = Box::new(Translation::new(Box::new(Sphere::new(1.0)), translation));
+            let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+            assert!(tree.add_actor(Rc::new(Actor::new(hitable, Box::new(PlainMaterial::new(texture))))));
+        }
+
+        let ray = Ray::from_array([-10.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        assert!(tree.any_hit(&ray, 0.0, 100.0));
+        assert!(!tree.any_hit(&ray, 0.0, 8.0));
+        let ray = Ray::from_array([-10.0, 2.0, 0.0], [1.0, 0.0, 0.0]);
+        assert!(!tree.any_hit(&ray, 0.0, 100.0));
+    }
+
+    #[test]
+    fn loose() {
+        // Spheres across the boundaries of the children of the root, and of one of its children
+        let bounds = BoundingBox::new(Vec3::from_array([-16.0; 3]), Vec3::from_array([16.0; 3]));
+        let actors = vec![
+            make_actor([0.0, 4.0, 4.0], 1.0),
+            make_actor([8.0, 0.0, 8.0], 1.0),
+            make_actor([-8.0, -8.0, 0.0], 1.0),
+            make_actor([8.0, 4.0, 4.0], 1.0)
+        ];
+
+        let mut tight = Octree::new(bounds.clone());
+        let mut options = OctreeOptions::new();
+        options.looseness = 2.0;
+        let mut loose = Octree::with_options(bounds, options);
+        for actor in &actors {
+            assert!(tight.add_actor(Rc::clone(actor)));
+            assert!(loose.add_actor(Rc::clone(actor)));
+        }
+
+        let stats = tight.get_stats();
+        assert_eq!(stats.actors_per_level[0], 3);
+        assert_eq!(stats.actors_per_level[1], 1);
+        let stats = loose.get_stats();
+        assert_eq!(stats.actors_per_level[0], 0);
+        assert_eq!(stats.actors_per_level[1], 0);
+        assert_eq!(stats.actors_per_level.iter().sum::<usize>(), 4);
+
+        for actor in &actors {
+            let center = actor.hitable.get_bounds().get_p1() - Vec3::from_array([1.0; 3]);
+            let ray = Ray::from_vec(&center + Vec3::from_array([0.0, 0.0, 10.0]), Vec3::from_array([0.0, 0.0, -1.0]));
+            match (tight.get_hit(&ray, 0.0, 100.0), loose.get_hit(&ray, 0.0, 100.0)) {
+                (Some((tight_actor, _)), Some((loose_actor, _))) => {
+                    assert!(Rc::ptr_eq(&tight_actor, actor));
+                    assert!(Rc::ptr_eq(&loose_actor, actor));
+                },
+                _ => {
+                    assert!(false);
+                }
+            }
+            assert!(loose.remove_actor(actor));
+        }
+        let stats = loose.get_stats();
+        assert_eq!((stats.node_count, stats.depth), (1, 1));
+    }
+
+    #[test]
+    fn depth_and_leaf_size() {
+        let bounds = BoundingBox::new(Vec3::from_array([-16.0; 3]), Vec3::from_array([16.0; 3]));
+        let mut options = OctreeOptions::new();
+        options.max_depth = 2;
+        let mut tree = Octree::with_options(bounds.clone(), options);
+        assert!(tree.add_actor(make_actor([1.0, 1.0, 1.0], 0.01)));
+        let stats = tree.get_stats();
+        assert_eq!((stats.node_count, stats.depth), (3, 3));
+        assert_eq!(stats.actors_per_level, [0, 0, 1]);
+
+        // The actors stay in the root until there are more than the leaf size
+        let mut options = OctreeOptions::new();
+        options.leaf_size = 2;
+        let mut tree = Octree::with_options(bounds, options);
+        assert!(tree.add_actor(make_actor([4.0, 4.0, 4.0], 1.0)));
+        assert!(tree.add_actor(make_actor([-4.0, 4.0, 4.0], 1.0)));
+        assert_eq!(tree.get_stats().actors_per_level, [2]);
+        assert!(tree.add_actor(make_actor([4.0, -4.0, 4.0], 1.0)));
+        let stats = tree.get_stats();
+        assert_eq!((stats.node_count, stats.depth), (4, 2));
+        assert_eq!(stats.actors_per_level, [0, 3]);
+    }
+
+    #[test]
+    fn grow() {
+        let bounds = BoundingBox::new(Vec3::from_array([-2.0; 3]), Vec3::from_array([2.0; 3]));
+        let mut tree = Octree::new(bounds);
+        let inside = make_actor([0.5, 0.5, 0.5], 0.25);
+        assert!(tree.add_actor(Rc::clone(&inside)));
+        let levels = tree.get_stats().depth;
+
+        // Doubling towards -x, +y, +z, then towards -x, +y, -z
+        let outside = make_actor([-9.0, 3.0, 1.0], 1.0);
+        assert!(tree.add_actor(Rc::clone(&outside)));
+        assert_eq!(tree.get_bounds().get_p0().get_data(), [-14.0, -2.0, -10.0]);
+        assert_eq!(tree.get_bounds().get_p1().get_data(), [2.0, 14.0, 6.0]);
+        let stats = tree.get_stats();
+        assert_eq!(stats.depth, levels + 2);
+        assert_eq!(stats.actors_per_level.iter().sum::<usize>(), 2);
+
+        for (actor, center) in &[(inside, [0.5, 0.5]), (outside, [-9.0, 3.0])] {
+            let ray = Ray::from_array([center[0], center[1], 50.0], [0.0, 0.0, -1.0]);
+            match tree.get_hit(&ray, 0.0, 100.0) {
+                Some((hit_actor, _)) => {
+                    assert!(Rc::ptr_eq(&hit_actor, actor));
+                },
+                None => {
+                    assert!(false);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stream() {
+        // Actors further and further away, in every direction
+        let bounds = BoundingBox::new(Vec3::from_array([0.0; 3]), Vec3::from_array([0.0; 3]));
+        let mut tree = Octree::new(bounds);
+        for i in 0..200 {
+            let distance = 2.0 * i as f64;
+            let sign = if i % 2 == 0 { 1.0 } else { -1.0 };
+            let center = [sign * distance, - sign * distance, distance];
+            assert!(tree.add_actor(make_actor(center, 0.5)));
+        }
+        let stats = tree.get_stats();
+        assert_eq!(stats.actors_per_level.iter().sum::<usize>(), 200);
+        assert!(stats.depth < 16);
+    }
 }
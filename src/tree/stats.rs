@@ -0,0 +1,189 @@
+use std::cell::Cell;
+
+// The shape of a tree, to compare the different tree types on a scene
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeStats {
+    pub node_count: usize,
+    pub depth: usize, // The number of levels, the root is the first one
+    // How many leaves hold each number of actors.
+    // Inner nodes that also hold actors (e.g. in octrees) are counted as leaves.
+    pub leaf_occupancy: Vec<usize>,
+    // How many actors the leaves of each level hold, actors in several leaves are counted in each
+    pub actors_per_level: Vec<usize>,
+    pub unbounded: usize, // Actors tested by every ray
+    pub memory: usize // Approximate size in bytes of the nodes and actor references, not of the actors
+}
+
+// What the trees did to answer the queries since the counters were last reset
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TraversalCounters {
+    pub rays: usize,
+    pub box_tests: usize,
+    pub primitive_tests: usize, // Actors tested against the ray
+    pub traversal_steps: usize // Nodes or grid cells visited
+}
+
+thread_local! {
+    static COUNTERS: Cell<TraversalCounters> = Cell::new(TraversalCounters::new());
+}
+
+impl TreeStats {
+    pub fn new() -> Self {
+        TreeStats {
+            node_count: 0,
+            depth: 0,
+            leaf_occupancy: Vec::new(),
+            actors_per_level: Vec::new(),
+            unbounded: 0,
+            memory: 0
+        }
+    }
+
+    pub fn get_leaf_count(&self) -> usize {
+        self.leaf_occupancy.iter().sum()
+    }
+
+    pub fn get_average_occupancy(&self) -> f64 {
+        let actors: usize = self.leaf_occupancy.iter().enumerate().map(|(count, leaves)| count * leaves).sum();
+        match self.get_leaf_count() {
+            0 => 0.0,
+            leaves => actors as f64 / leaves as f64
+        }
+    }
+
+    // Level is 0 for the root
+    pub(crate) fn add_node(&mut self, level: usize) {
+        self.node_count += 1;
+        if level >= self.depth {
+            self.depth = level + 1;
+        }
+    }
+
+    pub(crate) fn add_leaf(&mut self, level: usize, actor_count: usize) {
+        if self.leaf_occupancy.len() <= actor_count {
+            self.leaf_occupancy.resize(actor_count + 1, 0);
+        }
+        self.leaf_occupancy[actor_count] += 1;
+        if self.actors_per_level.len() <= level {
+            self.actors_per_level.resize(level + 1, 0);
+        }
+        self.actors_per_level[level] += actor_count;
+    }
+}
+
+impl TraversalCounters {
+    pub fn new() -> Self {
+        TraversalCounters {
+            rays: 0,
+            box_tests: 0,
+            primitive_tests: 0,
+            traversal_steps: 0
+        }
+    }
+
+    // A single measure of the work done, e.g. for heatmaps
+    pub fn get_cost(&self) -> usize {
+        self.box_tests + self.primitive_tests
+    }
+
+    pub fn get_box_tests_per_ray(&self) -> f64 {
+        self.get_per_ray(self.box_tests)
+    }
+
+    pub fn get_primitive_tests_per_ray(&self) -> f64 {
+        self.get_per_ray(self.primitive_tests)
+    }
+
+    pub fn get_traversal_steps_per_ray(&self) -> f64 {
+        self.get_per_ray(self.traversal_steps)
+    }
+
+    fn get_per_ray(&self, count: usize) -> f64 {
+        match self.rays {
+            0 => 0.0,
+            rays => count as f64 / rays as f64
+        }
+    }
+}
+
+// The counters are kept per thread, for all the trees traversed on it.
+// They only count with the stats feature, otherwise they stay at zero and cost nothing.
+pub fn get_counters() -> TraversalCounters {
+    COUNTERS.with(|counters| counters.get())
+}
+
+pub fn reset_counters() {
+    COUNTERS.with(|counters| counters.set(TraversalCounters::new()));
+}
+
+#[inline]
+fn count<F>(update: F)
+    where F: FnOnce(&mut TraversalCounters)
+{
+    if cfg!(feature = "stats") {
+        COUNTERS.with(|counters| {
+            let mut current = counters.get();
+            update(&mut current);
+            counters.set(current);
+        });
+    }
+}
+
+#[inline]
+pub(crate) fn count_ray() {
+    count(|counters| counters.rays += 1);
+}
+
+#[inline]
+pub(crate) fn count_box_test() {
+    count(|counters| counters.box_tests += 1);
+}
+
+#[inline]
+pub(crate) fn count_primitive_test() {
+    count(|counters| counters.primitive_tests += 1);
+}
+
+#[inline]
+pub(crate) fn count_traversal_step() {
+    count(|counters| counters.traversal_steps += 1);
+}
+
+pub(crate) fn get_vec_memory<V>(vec: &Vec<V>) -> usize {
+    vec.capacity() * std::mem::size_of::<V>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn occupancy() {
+        let mut stats = TreeStats::new();
+        stats.add_node(0);
+        stats.add_node(1);
+        stats.add_node(1);
+        stats.add_leaf(1, 3);
+        stats.add_leaf(1, 1);
+        assert_eq!(stats.node_count, 3);
+        assert_eq!(stats.depth, 2);
+        assert_eq!(stats.leaf_occupancy, [0, 1, 0, 1]);
+        assert_eq!(stats.actors_per_level, [0, 4]);
+        assert_eq!(stats.get_leaf_count(), 2);
+        assert_eq!(stats.get_average_occupancy(), 2.0);
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    fn counters() {
+        reset_counters();
+        count_ray();
+        count_box_test();
+        count_box_test();
+        count_primitive_test();
+        assert_eq!(get_counters().get_box_tests_per_ray(), 2.0);
+        assert_eq!(get_counters().get_cost(), 3);
+        reset_counters();
+        assert_eq!(get_counters(), TraversalCounters::new());
+    }
+}
@@ -93,6 +93,210 @@ pub fn reflection_probability<T>(direction: &Vec3<T>, normal: &Vec3<T>, n: T) ->
     r0 + (T::one() - r0) * pow5
 }
 
+pub fn orthonormal_basis<T>(direction: &Vec3<T>, up: &Vec3<T>) -> (Vec3<T>, Vec3<T>, Vec3<T>)
+    where T: Float
+{
+    // Returns the (u, v, w) camera basis, where w points along the view direction
+    let mut w = Vec3::from_slice(direction.get_data());
+    w.normalize();
+    let mut u = w.cross(up);
+    u.normalize();
+    let mut v = w.cross(&u);
+    v.normalize();
+    (u, v, w)
+}
+
+pub fn tangent_basis<T>(normal: &Vec3<T>) -> (Vec3<T>, Vec3<T>)
+    where T: Float
+{
+    // Returns a (tangent, bitangent) pair such that (tangent, bitangent, normal)
+    // is a right handed orthonormal basis. The tangent is the projection on the surface
+    // of the axis least aligned with the normal.
+    let mut index = 0;
+    for i in 1..3 {
+        if normal.get_data()[i].abs() < normal.get_data()[index].abs() {
+            index = i;
+        }
+    }
+    let mut axis = Vec3::<T>::new();
+    axis.get_data_mut()[index] = T::one();
+    let mut tangent = &axis - normal * axis.dot(normal);
+    tangent.normalize();
+    let mut bitangent = normal.cross(&tangent);
+    bitangent.normalize();
+    (tangent, bitangent)
+}
+
+pub fn step_past<T>(t: T) -> T
+    where T: Float
+{
+    // The smallest distance after t that doesn't hit the same surface again
+    let scale = T::from(1024.0).unwrap() * T::epsilon();
+    t + scale * (T::one() + t.abs())
+}
+
+pub fn azimuthal_tangent<T>(point: &Vec3<T>) -> Vec3<T>
+    where T: Float
+{
+    // The direction of increasing angle around the Z axis.
+    // Points on the axis get the direction at angle zero.
+    let x = point.get_data()[0];
+    let y = point.get_data()[1];
+    let r = (x * x + y * y).sqrt();
+    if r > T::zero() {
+        Vec3::from_array([- y / r, x / r, T::zero()])
+    } else {
+        Vec3::from_array([T::zero(), T::one(), T::zero()])
+    }
+}
+
+// Polynomial solvers used by the analytic primitives.
+// Real roots are returned in ascending order, repeated roots are only reported once.
+
+pub fn solve_quadratic<T>(a: T, b: T, c: T) -> Vec<T>
+    where T: Float
+{
+    if a == T::zero() {
+        if b == T::zero() {
+            return vec![];
+        }
+        return vec![- c / b];
+    }
+
+    let discriminant = b * b - T::from(4.0).unwrap() * a * c;
+    if discriminant < T::zero() {
+        return vec![];
+    }
+    if discriminant == T::zero() {
+        return vec![- b / (a + a)];
+    }
+
+    // Avoid the cancellation between b and the square root of the discriminant
+    let half = T::from(0.5).unwrap();
+    let q = if b < T::zero() {
+        - half * (b - discriminant.sqrt())
+    } else {
+        - half * (b + discriminant.sqrt())
+    };
+    let x0 = q / a;
+    let x1 = c / q;
+    if x0 < x1 { vec![x0, x1] } else { vec![x1, x0] }
+}
+
+pub fn solve_cubic<T>(a: T, b: T, c: T, d: T) -> Vec<T>
+    where T: Float
+{
+    if a == T::zero() {
+        return solve_quadratic(b, c, d);
+    }
+
+    // Normalize and substitute x = y - b / 3 to get y^3 + p * y + q = 0
+    let b = b / a;
+    let c = c / a;
+    let d = d / a;
+    let two = T::from(2.0).unwrap();
+    let three = T::from(3.0).unwrap();
+    let sub = b / three;
+    let p = c - b * b / three;
+    let q = two * b * b * b / T::from(27.0).unwrap() - b * c / three + d;
+
+    let p3 = p / three;
+    let q2 = q / two;
+    let discriminant = q2 * q2 + p3 * p3 * p3;
+
+    let mut roots = if discriminant > T::zero() {
+        // One real root
+        let s = discriminant.sqrt();
+        vec![(- q2 + s).cbrt() + (- q2 - s).cbrt()]
+    } else if discriminant == T::zero() {
+        if q2 == T::zero() {
+            vec![T::zero()]
+        } else {
+            let u = (- q2).cbrt();
+            vec![two * u, - u]
+        }
+    } else {
+        // Three real roots, use the trigonometric method
+        let pi = T::from(std::f64::consts::PI).unwrap();
+        let r = (- p3).sqrt();
+        let cos = - q2 / (r * r * r);
+        let cos = if cos > T::one() { T::one() } else if cos < - T::one() { - T::one() } else { cos };
+        let phi = cos.acos() / three;
+        vec![
+            two * r * phi.cos(),
+            two * r * (phi + two * pi / three).cos(),
+            two * r * (phi + T::from(4.0).unwrap() * pi / three).cos()
+        ]
+    };
+
+    for root in roots.iter_mut() {
+        *root = *root - sub;
+    }
+    roots.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    roots
+}
+
+pub fn solve_quartic<T>(a: T, b: T, c: T, d: T, e: T) -> Vec<T>
+    where T: Float
+{
+    if a == T::zero() {
+        return solve_cubic(b, c, d, e);
+    }
+
+    // Normalize and substitute x = y - b / 4 to get y^4 + p * y^2 + q * y + r = 0
+    let b = b / a;
+    let c = c / a;
+    let d = d / a;
+    let e = e / a;
+    let half = T::from(0.5).unwrap();
+    let two = T::from(2.0).unwrap();
+    let sub = b / T::from(4.0).unwrap();
+    let b2 = b * b;
+    let p = c - T::from(3.0 / 8.0).unwrap() * b2;
+    let q = b2 * b / T::from(8.0).unwrap() - half * b * c + d;
+    let r = - T::from(3.0 / 256.0).unwrap() * b2 * b2 + b2 * c / T::from(16.0).unwrap() - b * d / T::from(4.0).unwrap() + e;
+
+    let mut roots = if r == T::zero() {
+        // y * (y^3 + p * y + q) = 0
+        let mut roots = solve_cubic(T::one(), T::zero(), p, q);
+        roots.push(T::zero());
+        roots
+    } else {
+        // Ferrari's method: pick one root of the resolvent cubic
+        // and factor the quartic in two quadratics
+        let cubic = solve_cubic(T::one(), - half * p, - r, half * r * p - q * q / T::from(8.0).unwrap());
+        let z = cubic[cubic.len() - 1];
+
+        // The largest root makes both terms non negative, up to rounding errors
+        let u = z * z - r;
+        let v = two * z - p;
+        let u = if u > T::zero() { u.sqrt() } else { T::zero() };
+        let v = if v > T::zero() { v } else { T::zero() };
+        let v = if q < T::zero() { - v.sqrt() } else { v.sqrt() };
+
+        let mut roots = solve_quadratic(T::one(), v, z - u);
+        roots.append(&mut solve_quadratic(T::one(), - v, z + u));
+        roots
+    };
+
+    // Polish the roots on the original polynomial with a few Newton iterations
+    for root in roots.iter_mut() {
+        let mut x = *root - sub;
+        for _ in 0..2 {
+            let f = (((x + b) * x + c) * x + d) * x + e;
+            let df = ((T::from(4.0).unwrap() * x + T::from(3.0).unwrap() * b) * x + two * c) * x + d;
+            if df == T::zero() {
+                break;
+            }
+            x = x - f / df;
+        }
+        *root = x;
+    }
+    roots.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    roots.dedup();
+    roots
+}
+
 pub fn axis_to_index(axis: &Axis) -> usize {
     match axis {
         Axis::X => 0,
@@ -100,3 +304,44 @@ pub fn axis_to_index(axis: &Axis) -> usize {
         Axis::Z => 2
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_roots(roots: &[f64], expected: &[f64]) {
+        assert_eq!(roots.len(), expected.len());
+        for i in 0..roots.len() {
+            assert!((roots[i] - expected[i]).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn quadratic() {
+        // (x - 1) * (x - 3)
+        check_roots(&solve_quadratic(2.0, -8.0, 6.0), &[1.0, 3.0]);
+        check_roots(&solve_quadratic(1.0, -2.0, 1.0), &[1.0]);
+        check_roots(&solve_quadratic(1.0, 0.0, 1.0), &[]);
+        check_roots(&solve_quadratic(0.0, 2.0, -1.0), &[0.5]);
+    }
+
+    #[test]
+    fn cubic() {
+        // (x + 2) * (x - 1) * (x - 4)
+        check_roots(&solve_cubic(1.0, -3.0, -6.0, 8.0), &[-2.0, 1.0, 4.0]);
+        // (x - 2) * (x^2 + 1)
+        check_roots(&solve_cubic(2.0, -4.0, 2.0, -4.0), &[2.0]);
+    }
+
+    #[test]
+    fn quartic() {
+        // (x + 3) * (x + 1) * (x - 2) * (x - 5)
+        check_roots(&solve_quartic(1.0, -3.0, -15.0, 19.0, 30.0), &[-3.0, -1.0, 2.0, 5.0]);
+        // (x^2 - 4) * (x^2 + 1)
+        check_roots(&solve_quartic(1.0, 0.0, -3.0, 0.0, -4.0), &[-2.0, 2.0]);
+        // (x^2 + 1) * (x^2 + 2)
+        check_roots(&solve_quartic(1.0, 0.0, 3.0, 0.0, 2.0), &[]);
+        // x * (x - 1) * (x - 2) * (x - 3)
+        check_roots(&solve_quartic(1.0, -6.0, 11.0, -6.0, 0.0), &[0.0, 1.0, 2.0, 3.0]);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::float::Float;
 use std::ops;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Vec3<T: Float>
     where T: Float
 {
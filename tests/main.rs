@@ -10,6 +10,9 @@ use ray_tracer::hitable::primitive::Sphere;
 use ray_tracer::hitable::primitive::Rectangle;
 use ray_tracer::hitable::primitive::Cube;
 use ray_tracer::hitable::primitive::Group;
+use ray_tracer::hitable::primitive::Plane;
+use ray_tracer::hitable::primitive::Disk;
+use ray_tracer::hitable::primitive::Annulus;
 use ray_tracer::hitable::transform::Translation;
 use ray_tracer::camera::Camera;
 use ray_tracer::camera::perspective::PerspectiveCamera;
@@ -22,6 +25,9 @@ use ray_tracer::material::metal::MetalMaterial;
 use ray_tracer::material::dielectric::DielectricMaterial;
 use ray_tracer::actor::Actor;
 use ray_tracer::tree::TreeType;
+use ray_tracer::tree::oct::OctreeOptions;
+#[cfg(feature = "stats")]
+use ray_tracer::tree::stats;
 use ray_tracer::texture::uniform::UniformTexture;
 use ray_tracer::texture::checker::CheckerTexture;
 use ray_tracer::constants::Axis;
@@ -472,8 +478,7 @@ fn sphere_in_box() {
     }
 }
 
-#[test]
-fn random_scene() {
+fn create_random_scene() -> Scene<f64> {
     let mut scene = Scene::<f64>::new();
     // scene.set_background(Vec3::from_array([0.2, 0.2, 0.7]));
     scene.set_background(Vec3::from_array([0.5, 0.7, 0.9]));
@@ -589,10 +594,10 @@ fn random_scene() {
     let actor = Actor::<f64> { hitable, material };
     scene.add_actor(actor);
 
-    let mul = 120;
-    let width = 16 * mul;
-    let height = 9 * mul;
-    let aspect = width as f64 / height as f64;
+    scene
+}
+
+fn create_random_camera(aspect: f64) -> PerspectiveCamera<f64> {
     let mut camera = PerspectiveCamera::<f64>::new();
     camera.set_aspect(aspect);
     camera.set_fov(0.25 * std::f64::consts::PI);
@@ -607,6 +612,18 @@ fn random_scene() {
     camera.set_aperture(0.0);
     let focus = (camera.get_lookat() - camera.get_position()).norm();
     camera.set_focus(focus);
+    camera
+}
+
+#[test]
+fn random_scene() {
+    let mut scene = create_random_scene();
+
+    let mul = 120;
+    let width = 16 * mul;
+    let height = 9 * mul;
+    let aspect = width as f64 / height as f64;
+    let camera = create_random_camera(aspect);
 
     scene.set_tree_type(TreeType::Oct);
 
@@ -625,6 +642,161 @@ fn random_scene() {
     }
 }
 
+#[test]
+fn random_scene_traversal() {
+    // Compares the traversal of the trees on the random scene, without reflections
+    // so that the images are deterministic
+    let mut scene = create_random_scene();
+
+    let mul = 8;
+    let width = 16 * mul;
+    let height = 9 * mul;
+    let aspect = width as f64 / height as f64;
+    let camera = create_random_camera(aspect);
+    let renderer = Renderer::new(width, height, 0, 0, false);
+
+    scene.set_tree_type(TreeType::Linear);
+    let image_linear = renderer.render(&scene, &camera);
+
+    // Loose octree, keeping the actors straddling the cell boundaries out of the root
+    let mut options = OctreeOptions::new();
+    options.looseness = 1.5;
+    options.leaf_size = 4;
+
+    for &compact_tree in &[false, true] {
+        scene.set_compact_tree(compact_tree);
+        scene.set_octree_options(OctreeOptions::new());
+        for tree_type in vec![TreeType::Binary, TreeType::Oct, TreeType::Kd, TreeType::Grid] {
+            scene.set_tree_type(tree_type);
+            let image = renderer.render(&scene, &camera);
+            assert_eq!(image_diff(&image_linear, &image), 0.0);
+        }
+
+        scene.set_tree_type(TreeType::Oct);
+        scene.set_octree_options(options);
+        let image = renderer.render(&scene, &camera);
+        assert_eq!(image_diff(&image_linear, &image), 0.0);
+    }
+}
+
+#[test]
+#[ignore]
+fn random_scene_timing() {
+    // Wall clock comparison of the trees on the random scene, too noisy for the normal suite.
+    // Run with: cargo test --release -- --ignored --nocapture random_scene_timing
+    let mut scene = create_random_scene();
+
+    let mul = 16;
+    let width = 16 * mul;
+    let height = 9 * mul;
+    let aspect = width as f64 / height as f64;
+    let camera = create_random_camera(aspect);
+
+    // The primary rays, the hit search is timed without the shading
+    let rays: Vec<_> = (0..width * height).map(|k| {
+        let u = 2.0 * ((k % width) as f64 / width as f64) - 1.0;
+        let v = 2.0 * ((k / width) as f64 / height as f64) - 1.0;
+        camera.get_ray(u, v)
+    }).collect();
+
+    // The best of a few runs, to smooth out the noise
+    let get_time = |scene: &Scene<f64>| {
+        (0..3).map(|_| {
+            let now = Instant::now();
+            let hits = rays.iter().filter(|ray| scene.intersect(ray).is_some()).count();
+            assert!(hits > 0);
+            now.elapsed().as_micros()
+        }).min().unwrap()
+    };
+    let rays_per_second = |t: u128| rays.len() as u128 * 1_000_000 / t;
+
+    scene.set_tree_type(TreeType::Linear);
+    let t_linear = get_time(&scene);
+
+    // The grid and the kd-tree are never flattened
+    scene.set_tree_type(TreeType::Grid);
+    assert!(get_time(&scene) < t_linear);
+    scene.set_tree_type(TreeType::Kd);
+    assert!(get_time(&scene) < t_linear);
+
+    // Each tree is faster than testing all the actors, and its flattened copy is at least as fast.
+    // The two layouts are timed in turns, so that a change of the machine load hits both,
+    // and the median of the turns is kept, so that a burst of load during a few of them doesn't count.
+    let median = |mut times: Vec<u128>| {
+        times.sort();
+        times[times.len() / 2]
+    };
+    for (name, tree_type) in vec![("binary", TreeType::Binary), ("oct", TreeType::Oct)] {
+        scene.set_tree_type(tree_type);
+        let (mut times, mut times_flat) = (Vec::new(), Vec::new());
+        for _ in 0..7 {
+            scene.set_compact_tree(false);
+            times.push(get_time(&scene));
+            scene.set_compact_tree(true);
+            times_flat.push(get_time(&scene));
+        }
+        let (t, t_flat) = (median(times), median(times_flat));
+        println!("{}: {} rays/s, flattened {} rays/s", name, rays_per_second(t), rays_per_second(t_flat));
+        assert!(t < t_linear);
+        assert!(t_flat <= t);
+    }
+}
+
+#[test]
+fn tree_stats() {
+    // The shape of each tree on the random scene
+    let mut scene = create_random_scene();
+
+    scene.set_tree_type(TreeType::Linear);
+    let linear = scene.get_tree_stats();
+    assert_eq!(linear.node_count, 1);
+    assert_eq!(linear.get_leaf_count(), 1);
+
+    for tree_type in vec![TreeType::Binary, TreeType::Oct, TreeType::Kd, TreeType::Grid] {
+        scene.set_tree_type(tree_type);
+        let stats = scene.get_tree_stats();
+        assert!(stats.node_count > 1);
+        assert!(stats.depth > 1);
+        assert!(stats.memory > 0);
+        assert!(stats.get_average_occupancy() < linear.get_average_occupancy());
+    }
+}
+
+#[test]
+#[cfg(feature = "stats")]
+fn traversal_counters() {
+    // The work each tree does to render the random scene.
+    // Run with: cargo test --features stats traversal_counters
+    let mut scene = create_random_scene();
+    let width = 32;
+    let height = 18;
+    let camera = create_random_camera(width as f64 / height as f64);
+    let renderer = Renderer::new(width, height, 0, 0, false);
+
+    scene.set_tree_type(TreeType::Linear);
+    stats::reset_counters();
+    renderer.render(&scene, &camera);
+    let linear_counters = stats::get_counters();
+    assert_eq!(linear_counters.rays, width * height);
+    assert_eq!(linear_counters.box_tests, 0);
+
+    for tree_type in vec![TreeType::Binary, TreeType::Oct, TreeType::Kd, TreeType::Grid] {
+        scene.set_tree_type(tree_type);
+        stats::reset_counters();
+        renderer.render(&scene, &camera);
+        let counters = stats::get_counters();
+        assert_eq!(counters.rays, width * height);
+        assert!(counters.box_tests > 0);
+        assert!(counters.primitive_tests < linear_counters.primitive_tests);
+    }
+
+    let heatmap = renderer.render_heatmap(&scene, &camera);
+    assert_eq!(heatmap.data.len(), 3 * width * height);
+    assert!(heatmap.data.iter().all(|&value| value >= 0.0 && value <= 1.0));
+    assert!(heatmap.data.chunks(3).any(|color| color == [1.0, 0.0, 0.0]));
+    assert_eq!(stats::get_counters().rays, 0);
+}
+
 #[test]
 fn tree() {
     let mut scene = Scene::<f64>::new();
@@ -713,3 +885,83 @@ fn tree() {
     assert_eq!(diff, 0.0);
     // println!("Oct -  t: {}  diff: {}", t_oct, diff);
 }
+
+#[test]
+fn unbounded() {
+    let mut scene = Scene::<f64>::new();
+    scene.set_background(Vec3::from_array([0.6, 0.8, 1.0]));
+
+    // Infinite plane used as floor
+    let hitable = Box::new(Plane::new(Vec3::from_array([0.0, 0.0, 1.0])));
+    let texture0 = Box::new(UniformTexture::new(Vec3::from_array([0.9, 0.9, 0.9])));
+    let texture1 = Box::new(UniformTexture::new(Vec3::from_array([0.2, 0.2, 0.2])));
+    let texture = Box::new(CheckerTexture::new(texture0, texture1));
+    let material = Box::new(LambertianMaterial::<f64>::new(texture, 0.65));
+    let actor = Actor::<f64> { hitable, material};
+    scene.add_actor(actor);
+
+    let hitable = Box::new(Sphere::new(1.0));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, 1.0])));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.2, 0.2])));
+    let material = Box::new(LambertianMaterial::<f64>::new(texture, 0.65));
+    let actor = Actor::<f64> { hitable, material};
+    scene.add_actor(actor);
+
+    let hitable = Box::new(Disk::new(1.0, Vec3::from_array([0.0, -1.0, 0.0])));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([-2.5, 0.0, 1.0])));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([0.2, 1.0, 0.2])));
+    let material = Box::new(LambertianMaterial::<f64>::new(texture, 0.65));
+    let actor = Actor::<f64> { hitable, material};
+    scene.add_actor(actor);
+
+    let hitable = Box::new(Annulus::new(0.5, 1.0, Vec3::from_array([0.0, -1.0, 1.0])));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([2.5, 0.0, 1.0])));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([0.2, 0.2, 1.0])));
+    let material = Box::new(LambertianMaterial::<f64>::new(texture, 0.65));
+    let actor = Actor::<f64> { hitable, material};
+    scene.add_actor(actor);
+
+    let mul = 8;
+    let width = 16 * mul;
+    let height = 9 * mul;
+    let aspect = width as f64 / height as f64;
+    let mut camera = PerspectiveCamera::<f64>::new();
+    camera.set_aspect(aspect);
+    camera.set_fov(0.3 * std::f64::consts::PI);
+    camera.set_position(&[0.0, -10.0, 3.0]);
+    camera.set_lookat(&[0.0, 0.0, 1.0]);
+    camera.set_up(&[0.0, 0.0, 1.0]);
+
+    let renderer = Renderer::new(width, height, 0, 0, false);
+
+    scene.set_tree_type(TreeType::Linear);
+    let image_linear = renderer.render(&scene, &camera);
+
+    scene.set_tree_type(TreeType::Binary);
+    let image_binary = renderer.render(&scene, &camera);
+    assert_eq!(image_diff(&image_linear, &image_binary), 0.0);
+
+    scene.set_tree_type(TreeType::Oct);
+    let image_oct = renderer.render(&scene, &camera);
+    assert_eq!(image_diff(&image_linear, &image_oct), 0.0);
+
+    scene.set_tree_type(TreeType::Kd);
+    let image_kd = renderer.render(&scene, &camera);
+    assert_eq!(image_diff(&image_linear, &image_kd), 0.0);
+
+    scene.set_tree_type(TreeType::Grid);
+    let image_grid = renderer.render(&scene, &camera);
+    assert_eq!(image_diff(&image_linear, &image_grid), 0.0);
+
+    // The unbounded actors are kept aside by the flattened trees too
+    scene.set_compact_tree(true);
+    for tree_type in vec![TreeType::Linear, TreeType::Binary, TreeType::Oct, TreeType::Kd, TreeType::Grid] {
+        scene.set_tree_type(tree_type);
+        let image = renderer.render(&scene, &camera);
+        assert_eq!(image_diff(&image_linear, &image), 0.0);
+    }
+
+    // The floor covers the bottom of the image
+    let index = (height - 1) * width;
+    assert!(image_oct.data[3 * index] != 0.6);
+}